@@ -0,0 +1,45 @@
+/*
+    Memoized values derived from a monitor
+*/
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::OnMutate;
+
+/// A derived value that's only recomputed when the source monitor changed since the last `get()`
+pub struct Computed<U: Clone> {
+    writes: Rc<Cell<u64>>, // The source's write counter
+    compute: Box<dyn Fn() -> U>,
+    cache: RefCell<Option<(u64, U)>>, // Write count the cached value was computed at
+}
+
+impl<U: Clone> Computed<U> {
+    /// The cached value, or a freshly computed one if the source changed
+    pub fn get(&self) -> U {
+        let writes = self.writes.get();
+
+        if let Some((cached_at, value)) = &*self.cache.borrow() {
+            if *cached_at == writes {
+                return value.clone();
+            }
+        }
+
+        let value = (self.compute)();
+        *self.cache.borrow_mut() = Some((writes, value.clone()));
+        value
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> OnMutate<T> {
+    /// Derive a memoized value from this monitor; `compute` only runs when the value changed (or was rewritten past a comparator) since the last access
+    pub fn computed<U: Clone>(&self, compute: impl Fn(&T) -> U + 'static) -> Computed<U> {
+        let value = self.mut_value.clone();
+
+        Computed {
+            writes: self.writes.clone(),
+            compute: Box::new(move || compute(&value.borrow())),
+            cache: RefCell::new(None),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/*
+    Extension traits adding observed operations for common collection types
+*/
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::OnMutate;
+
+/// Observed operations for `OnMutate<Vec<E>>`; each emits at most one event
+pub trait VecOnMutateExt<E> {
+    /// Push an item onto the end
+    fn push_observed(&self, item: E);
+
+    /// Pop the last item; popping an empty vector emits nothing
+    fn pop_observed(&self) -> Option<E>;
+
+    /// Remove every item; clearing an empty vector emits nothing
+    fn clear_observed(&self);
+
+    /// Keep only the items `pred` accepts, like `Vec::retain`; removing nothing emits nothing
+    fn retain_observed(&self, pred: impl Fn(&E) -> bool);
+
+    /// Insert into an already-sorted vector, after any equal items, returning the index
+    ///
+    /// Emitted as a `RangeChange` (an empty range at the index, with the item inserted) to `on_range_change()` listeners, like `replace_range_observed()`; the main callback and subscribers aren't notified
+    ///
+    fn insert_sorted_observed(&self, item: E) -> usize
+    where E: Ord + 'static;
+}
+
+impl<E: Clone + PartialEq> VecOnMutateExt<E> for OnMutate<Vec<E>> {
+    fn push_observed(&self, item: E) {
+        self.with_mut(None, |v| v.push(item));
+    }
+
+    fn pop_observed(&self) -> Option<E> {
+        self.with_mut(None, |v| v.pop())
+    }
+
+    fn clear_observed(&self) {
+        self.with_mut(None, |v| v.clear());
+    }
+
+    fn retain_observed(&self, pred: impl Fn(&E) -> bool) {
+        self.with_mut(None, |v| v.retain(|item| pred(item)));
+    }
+
+    fn insert_sorted_observed(&self, item: E) -> usize
+    where E: Ord + 'static
+    {
+        let idx = self.with_ref(|v| v.partition_point(|x| *x <= item));
+        self.replace_range_observed(idx..idx, vec![item]);
+        idx
+    }
+}
+
+/// Observed operations for `OnMutate<HashMap<K, V>>`; each emits at most one event
+pub trait MapOnMutateExt<K, V> {
+    /// Insert a key/value pair, returning the previous value; re-inserting an identical pair emits nothing
+    fn insert_observed(&self, key: K, value: V) -> Option<V>;
+
+    /// Remove a key, returning its value; removing a missing key emits nothing
+    fn remove_observed(&self, key: &K) -> Option<V>;
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq> MapOnMutateExt<K, V> for OnMutate<HashMap<K, V>> {
+    fn insert_observed(&self, key: K, value: V) -> Option<V> {
+        self.with_mut(None, |m| m.insert(key, value))
+    }
+
+    fn remove_observed(&self, key: &K) -> Option<V> {
+        self.with_mut(None, |m| m.remove(key))
+    }
+}
+
+/// Observed operations for `OnMutate<String>`; each emits at most one event
+pub trait StringOnMutateExt {
+    /// Append a string slice; appending `""` emits nothing
+    fn push_str_observed(&self, s: &str);
+
+    /// Shorten to `len` bytes (panics off a char boundary, like `String::truncate`); truncating past the end emits nothing
+    fn truncate_observed(&self, len: usize);
+
+    /// Empty the string; clearing an empty string emits nothing
+    fn clear_observed(&self);
+}
+
+impl StringOnMutateExt for OnMutate<String> {
+    fn push_str_observed(&self, s: &str) {
+        self.with_mut(None, |v| v.push_str(s));
+    }
+
+    fn truncate_observed(&self, len: usize) {
+        self.with_mut(None, |v| v.truncate(len));
+    }
+
+    fn clear_observed(&self) {
+        self.with_mut(None, |v| v.clear());
+    }
+}
@@ -0,0 +1,81 @@
+/*
+    Monitors whose initial value is computed on first use
+*/
+
+use std::cell::{OnceCell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{Callback, Mutate, OnMutate};
+
+/// Boxed initializer that `LazyOnMutate` runs on first use
+type Init<T> = Box<dyn FnOnce() -> T + 'static>;
+
+/// `OnMutate<T>` whose initial value comes from an init closure, run the first time the value is needed
+///
+/// Creating the value doesn't emit an event; the first mutation compares against whatever `init` produced. Use `get_val_or()` to peek without initializing
+///
+/// Full type implementation: `impl<T: Clone + PartialEq> LazyOnMutate<T> {}`
+///
+pub struct LazyOnMutate<T: Clone + PartialEq> {
+    pending: RefCell<Option<(Init<T>, Callback<T>)>>, // Until first use: the init closure and the callback to hand the monitor
+    inner: OnceCell<OnMutate<T>>, // Monitor, once initialized
+}
+
+impl<T: Clone + PartialEq> fmt::Debug for LazyOnMutate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyOnMutate")
+            .field("pending", &self.pending.borrow().as_ref().map(|_| "<init>"))
+            .field("inner", &self.inner.get())
+            .finish()
+    }
+}
+
+impl<T: Clone + PartialEq> OnMutate<T> {
+    /// New monitor whose value is produced by `init` on first use (see `LazyOnMutate`)
+    pub fn new_lazy<I, F>(init: I, callback: F) -> LazyOnMutate<T>
+    where
+        I: FnOnce() -> T + 'static,
+        F: FnMut(&Mutate<T>) + 'static,
+    {
+        LazyOnMutate {
+            pending: RefCell::new(Some((Box::new(init), Box::new(callback)))),
+            inner: OnceCell::new(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> LazyOnMutate<T> {
+    /// Has the init closure run yet?
+    pub fn is_initialized(&self) -> bool {
+        self.inner.get().is_some()
+    }
+
+    /// Get the current value, initializing it first if needed
+    pub fn get_val(&self) -> T {
+        self.monitor().get_val()
+    }
+
+    /// Get the current value if it's initialized, `default` otherwise; never runs the init closure
+    pub fn get_val_or(&self, default: T) -> T {
+        self.inner.get().map_or(default, OnMutate::get_val)
+    }
+
+    /// Replace the entire value (initializing it first, so the event's `old` is the initial value); notify if different
+    pub fn replace(&self, new_value: T) {
+        self.monitor().replace(new_value);
+    }
+
+    /// Mutate (initializing first if needed); notify once if changed + add a context tag if applicable
+    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        self.monitor().with_mut(tag, f)
+    }
+
+    /// The underlying monitor, initializing it first if needed
+    pub fn monitor(&self) -> &OnMutate<T> {
+        self.inner.get_or_init(|| {
+            let (init, callback) = self.pending.borrow_mut().take().expect("lazy monitor initialized twice");
+            OnMutate::new_in(Rc::new(RefCell::new(init())), callback)
+        })
+    }
+}
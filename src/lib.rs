@@ -22,172 +22,1954 @@
     Project: Mutation Monitor (https://github.com/umikoio/mutation-monitor)
 */
 
-use std::cell::{Cell, RefCell, RefMut};
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cell::{BorrowMutError, Cell, Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::{Rc, Weak};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+mod computed;
+mod ext;
+mod lazy;
+mod macros;
+mod path;
+mod rc;
+mod sink;
+mod typed;
+
+mod sync;
+mod try_clone;
+
+#[cfg(feature = "serde_json")]
+mod patch;
+
+#[cfg(feature = "serde")]
+mod state;
+
+#[cfg(feature = "futures")]
+mod stream;
+
+pub use computed::Computed;
+pub use ext::{MapOnMutateExt, StringOnMutateExt, VecOnMutateExt};
+pub use lazy::LazyOnMutate;
+pub use path::{PathWatcher, Projected};
+pub use rc::OnMutateRc;
+pub use sink::{ClosureSink, EventSink, VecSink};
+pub use sync::SyncOnMutate;
+pub use try_clone::{TryClone, TryOnMutate};
+pub use typed::{float_eq, IndexChange, RangeChange};
+
+#[cfg(feature = "serde")]
+pub use state::SerializableState;
+
+#[cfg(feature = "futures")]
+pub use stream::MutateStream;
+
+#[cfg(feature = "channel")]
+pub use sink::ChannelSink;
+
+/// Everything needed for the common cases: `use mutation_monitor::prelude::*;`
+pub mod prelude {
+    pub use crate::{HasLen, MapOnMutateExt, Mutate, OnMutate, StringOnMutateExt, VecOnMutateExt};
+}
+
+/// Boxed callback invoked for every delivered mutation
+type Callback<T> = Box<dyn FnMut(&Mutate<T>) + 'static>;
+
+/// Decides whether a subscriber receives an event
+type Filter<T> = Box<dyn Fn(&Mutate<T>) -> bool + 'static>;
+
+/// Diagnostic subscriber that also sees filtered events, see `subscribe_raw()`
+type RawCallback<T> = Box<dyn FnMut(&Mutate<T>, Delivered) + 'static>;
+
+/// Observes the value around a mutation attempt
+type Hook<T> = Box<dyn FnMut(&T) + 'static>;
+
+/// Custom equality used for change detection
+type Comparator<T> = Box<dyn Fn(&T, &T) -> bool + 'static>;
+
+/// Rewrites a tag before it's stored on a `Mutate`
+type TagNormalizer = Box<dyn Fn(&str) -> String + 'static>;
+
+/// Boxed tag rewrite applied at delivery, see `with_tag_map()`
+type TagMap = Box<dyn Fn(Option<String>) -> Option<String> + 'static>;
+
+/// Boxed debug sink for lifecycle labels, see `with_inspect()`
+type Inspector = Box<dyn Fn(&str) + 'static>;
+
+/// Boxed version/generation accessor, see `with_version()`
+type VersionFn<T> = Box<dyn Fn(&T) -> u64 + 'static>;
+
+/// Runs on the event a guard builds as it drops, see `on_drop_result()`
+type DropHook<'a, T> = Box<dyn FnOnce(&Mutate<T>) + 'a>;
+
+/// Boxed time source, see `with_clock()`
+type Clock = Box<dyn Fn() -> Instant + 'static>;
+
+/// Boxed state check, see `with_invariant()`
+type Invariant<T> = Box<dyn Fn(&T) -> bool + 'static>;
+
+/// Gets `(old_key, new_key)` for a regression and returns whether to reject it, see `with_monotonic()`
+type RegressionHandler = Box<dyn FnMut(u64, u64) -> bool + 'static>;
+
+/// Why an event was emitted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    /// The value changed
+    #[default]
+    Changed,
+    /// Emitted on request (`replace_forced()`, `MutationCtx::force()`), so `old` may equal `new`
+    Forced,
+}
 
 /// Monitor mutations via a struct to contain the data
 #[derive(Clone, Debug, PartialEq)]
-pub struct Mutate<T: Clone + PartialEq> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mutate<T> {
     pub old: T,
     pub new: T,
     pub tag: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: EventKind,
+}
+
+impl<T: Clone + PartialEq> Mutate<T> {
+    fn new(old: T, new: T, tag: Option<String>) -> Self
+    {
+        Self { old, new, tag, kind: EventKind::Changed }
+    }
+
+    fn forced(old: T, new: T, tag: Option<String>) -> Self
+    {
+        Self { old, new, tag, kind: EventKind::Forced }
+    }
+
+    /// Was this event emitted on request rather than because the value changed?
+    pub fn is_forced(&self) -> bool {
+        self.kind == EventKind::Forced
+    }
+
+    /// Is this event tagged with exactly `tag`?
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tag.as_deref() == Some(tag)
+    }
+
+    /// Does this event carry any tag?
+    pub fn is_tagged(&self) -> bool {
+        self.tag.is_some()
+    }
+
+    /// The tag, or `default` for untagged events
+    pub fn tag_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.tag.as_deref().unwrap_or(default)
+    }
+
+    /// True when the event didn't actually change anything (`old == new`)
+    pub fn is_noop(&self) -> bool {
+        self.old == self.new
+    }
+}
+
+/// Collapse a recorded slice of events into the net change (old of the first, new of the last)
+///
+/// Returns `None` for an empty slice or when the values ended up back where they started. The tag is kept only when every event shares it
+///
+pub fn net_change<T: Clone + PartialEq>(events: &[Mutate<T>]) -> Option<Mutate<T>> {
+    let (first, last) = (events.first()?, events.last()?);
+
+    if first.old == last.new {
+        return None;
+    }
+
+    let tag = if events.iter().all(|evt| evt.tag == first.tag) { first.tag.clone() } else { None };
+    Some(Mutate::new(first.old.clone(), last.new.clone(), tag))
+}
+
+/// Merge runs of adjacent events sharing a (non-`None`) tag, see `with_tag_coalescing()`
+fn coalesce_tags<T: Clone + PartialEq>(batch: VecDeque<Mutate<T>>) -> VecDeque<Mutate<T>> {
+    let mut out = VecDeque::with_capacity(batch.len());
+    let mut run: Vec<Mutate<T>> = Vec::new();
+
+    for new_event in batch {
+        if run.last().is_some_and(|last| last.tag.is_none() || last.tag != new_event.tag) {
+            out.extend(flush_run(&mut run));
+        }
+
+        run.push(new_event);
+    }
+
+    out.extend(flush_run(&mut run));
+    out
+}
+
+/// Collapse a run into one event (old of the first, new of the last), `Forced` if any of them was; single events pass through untouched
+fn flush_run<T: Clone + PartialEq>(run: &mut Vec<Mutate<T>>) -> Option<Mutate<T>> {
+    let last = run.pop()?;
+
+    if run.is_empty() {
+        return Some(last);
+    }
+
+    let forced = last.is_forced() || run.iter().any(Mutate::is_forced);
+    let first = run.swap_remove(0);
+    run.clear();
+
+    Some(Mutate {
+        old: first.old,
+        new: last.new,
+        tag: last.tag,
+        kind: if forced { EventKind::Forced } else { EventKind::Changed },
+    })
+}
+
+/// An event kept by `with_history()`, stamped with when it was queued if the `timestamps` feature is on
+#[derive(Clone, Debug)]
+struct HistoryEntry<T> {
+    event: Mutate<T>,
+    #[cfg(feature = "timestamps")]
+    at: Instant,
+}
+
+/// User context owned by a monitor made with `new_with_ctx()`, stored as an extension
+struct ContextSlot<C>(RefCell<C>);
+
+/// When the latest event was queued, stored as an extension for `time_since_last_change()`
+#[cfg(feature = "timestamps")]
+struct LastChange(Cell<Option<Instant>>);
+
+/// Oldest a history entry may get, stored as an extension by `with_history_max_age()`
+#[cfg(feature = "timestamps")]
+struct HistoryMaxAge(Cell<Duration>);
+
+/// Key that `tag_counts()` uses for untagged events
+pub const UNTAGGED: &str = "<untagged>";
+
+/// Handle returned by `subscribe()` and friends, used to `unsubscribe()` later
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// A subscriber's filter and callback, taken out while it's being invoked
+struct Handler<T: Clone + PartialEq> {
+    filter: Option<Filter<T>>,
+    callback: Callback<T>,
+}
+
+struct Subscriber<T: Clone + PartialEq> {
+    id: SubscriptionId,
+    priority: i32, // Higher priorities are notified first
+    once: bool, // Removed after the first event it receives
+    retired: Option<Rc<Cell<bool>>>, // Set by an `add_listener_while()` listener that's done; removed after the current event
+    handler: Option<Handler<T>>, // `None` while the handler is running
+}
+
+/// Handle returned by `checkpoint()`, used with `diff_since()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CheckpointId(u64);
+
+/// Whether an event seen by a `subscribe_raw()` subscriber made it past `with_filter()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivered {
+    /// Reached the callback and subscribers
+    Passed,
+    /// Dropped by the filter
+    Filtered,
+}
+
+/// A `subscribe_stable()` subscriber and the last value it was given
+struct Stable<T: Clone + PartialEq> {
+    id: SubscriptionId,
+    last: Option<T>,
+    callback: Option<Hook<T>>, // `None` while the callback is running
+}
+
+/// A `subscribe_debounced()` subscriber: its latest net change waits here until `poll()` finds it due
+struct Debounced<T: Clone + PartialEq> {
+    id: SubscriptionId,
+    delay: Duration,
+    pending: Option<(Mutate<T>, Instant)>, // Net change since the last delivery, and when it's due
+    callback: Option<Callback<T>>, // `None` while the callback is running
+}
+
+/// What a capped queue (see `with_max_queue()`) does when it's full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Evict the oldest queued event to make room
+    DropOldest,
+    /// Discard the incoming event
+    DropNewest,
+    /// Make room by delivering the queue right away, ignoring any event budget; nothing can wait on a single thread, so this never actually blocks the caller
+    ///
+    /// Where events mustn't be delivered yet (a buffered, batching or detached monitor, one that's already draining, or a guard holding the value), they go over the cap instead. `SyncOnMutate` has no queue cap
+    ///
+    Block,
+}
+
+/// What `with_invariant()` does with a change that breaks the invariant
+pub enum Violation<T> {
+    /// Panic, debug-assert style
+    Panic,
+    /// Report `"invariant violated"` to the `with_inspect()` sink and deliver the event as usual
+    Log,
+    /// Hand the offending value to a handler and deliver the event as usual
+    Call(Box<dyn Fn(&T) + 'static>),
+    /// Restore the pre-mutation value and drop the event (a guard's `checkpoint()` still holds the value, so its events can't be rolled back and are kept)
+    Rollback,
+    /// `Rollback`, also handing the rejected value to a handler
+    RollbackWith(Box<dyn Fn(&T) + 'static>),
+}
+
+impl<T> fmt::Debug for Violation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Panic => f.write_str("Panic"),
+            Violation::Log => f.write_str("Log"),
+            Violation::Call(_) => f.write_str("Call(<handler>)"),
+            Violation::Rollback => f.write_str("Rollback"),
+            Violation::RollbackWith(_) => f.write_str("RollbackWith(<handler>)"),
+        }
+    }
+}
+
+/// A value `replace_checked()` refused because it broke the invariant; the monitor was left untouched
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantError<T> {
+    pub rejected: T,
+}
+
+impl<T> InvariantError<T> {
+    /// Take the rejected value back
+    pub fn into_inner(self) -> T {
+        self.rejected
+    }
+}
+
+impl<T> fmt::Display for InvariantError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("value rejected by invariant")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for InvariantError<T> {}
+
+/// Public observable wrapper for mutations
+///
+/// We maintain borrow checks (to avoid BorrowMutError) by draining a queue, this way we never make a call while a borrow is held
+///
+/// `OnMutate` is neither `Send` nor `Sync` (it's built on `Rc`/`RefCell`); use `SyncOnMutate` to share a monitored value across threads
+///
+/// ```compile_fail,E0277
+/// fn require_sync<T: Sync>(_: &T) {}
+///
+/// let on = mutation_monitor::OnMutate::new(0, |_| {});
+/// require_sync(&on);
+/// ```
+///
+/// Full type implementation: `impl<T: Clone + PartialEq> OnMutate<T> {}`
+///
+pub struct OnMutate<T: Clone + PartialEq> {
+    mut_value: Rc<RefCell<T>>, // Actual value being ingested (possibly shared, see `from_shared()`)
+    external_baseline: Option<RefCell<T>>, // Last value this monitor saw, only tracked for shared values
+    callback_ref: RefCell<Option<Callback<T>>>, // Callback for the ingested value
+    subscribers: RefCell<Vec<Subscriber<T>>>, // Additional subscribers, notified after the main callback
+    raw_subscribers: RefCell<Vec<(SubscriptionId, RawCallback<T>)>>, // See every drained event, filtered or not
+    debounced: RefCell<Vec<Debounced<T>>>, // Subscribers delivered through `poll()` once events settle
+    stable: RefCell<Vec<Stable<T>>>, // Given the value once a drain fully settles
+    notifying_stable: Cell<bool>, // Are stable subscribers being notified? Drains they cause leave it to the running pass
+    next_id: Cell<u64>, // Next `SubscriptionId` to hand out
+    filter: Option<Filter<T>>, // Events it rejects are drained without reaching the callback or subscribers
+    queue: RefCell<VecDeque<Mutate<T>>>, // Simple queue for maintaing incoming data
+    max_queue: Option<(usize, OverflowPolicy)>, // Optional cap on the queue and what to do when it's hit
+    buffered: bool, // Buffered monitors only deliver on `drain_now()`
+    flush_on_drop: bool, // Deliver whatever is still queued when the monitor drops
+    event_budget: Option<usize>, // Most events a single automatic drain delivers
+    draining: Cell<bool>, // Is the queue currently draining?
+    mutating: Cell<bool>, // Is a `with_mut()`/guard currently holding the value?
+    paused: Cell<bool>, // Inside `pause_during()`: new events are discarded
+    detached: Cell<bool>, // The callback was taken with `take_callback()`: events wait in the queue
+    batch_depth: Cell<u32>, // Nesting of `batch()`/`transaction()`; automatic drains wait for the outermost one
+    tag_normalizer: Option<TagNormalizer>, // Applied to every tag as the event is queued
+    tag_map: Option<TagMap>, // Applied to every tag (even `None`) as the event is delivered
+    tag_coalescing: bool, // Merge adjacent same-tag events in a batch before delivery
+    tag_stats: RefCell<HashMap<String, u64>>, // Delivered events per tag (`UNTAGGED` for the rest)
+    pre_hook: RefCell<Option<Hook<T>>>, // Runs before every mutation attempt
+    post_hook: RefCell<Option<Hook<T>>>, // Runs after every mutation attempt
+    extensions: RefCell<HashMap<TypeId, Rc<dyn Any>>>, // Typed state for features with extra bounds on `T`
+    seq: Rc<Cell<u64>>, // Bumped on every change made through the monitor
+    writes: Rc<Cell<u64>>, // Bumped on every change, and on any write under a comparator or version; keys cached copies (shared with `Computed`)
+    comparator: Option<Comparator<T>>, // Replaces `PartialEq` for change detection
+    inspector: Option<Inspector>, // Debug sink for lifecycle labels
+    clock: Option<Clock>, // Time source for time-based features (`Instant::now` if unset)
+    debounce: Option<Duration>, // Quiet period `replace_debounced()` waits for
+    pending: RefCell<Option<(T, Instant)>>, // Debounced value and when it's due
+    version: Option<VersionFn<T>>, // Cheap generation counter checked before full equality
+    trust_version: bool, // Treat differing versions as a change without comparing values
+    lazy_eq: bool, // `replace()` queues without comparing; equal events are dropped at drain
+    recent: Option<(usize, bool, RefCell<VecDeque<T>>)>, // Window, whether suppressed values still apply, and the most recent distinct values changed to; see `with_recent_dedup()`
+    invariant: Option<(Invariant<T>, Violation<T>)>, // Checked against every change as it's queued
+    monotonic: Option<(VersionFn<T>, RefCell<Option<RegressionHandler>>)>, // Key that mustn't go backwards, and its handler (`None` while running)
+    history_cap: Option<usize>, // Most events `history()` keeps (`None` keeps no history)
+    history: RefCell<VecDeque<HistoryEntry<T>>>, // Recent events, oldest first
+    checkpoints: RefCell<HashMap<CheckpointId, T>>, // Snapshots taken by `checkpoint()`
+    next_checkpoint: Cell<u64>, // Next `CheckpointId` to hand out
+    val_cache: RefCell<Option<(u64, Rc<T>)>>, // Clone handed out by `get_val_cached()`, and the `writes` count it was taken at
+}
+
+impl<T: Clone + PartialEq> fmt::Debug for OnMutate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnMutate")
+            .field("mut_value", &"<value>")
+            .field("external_baseline", &self.external_baseline.as_ref().map(|_| "<baseline>"))
+            .field("callback_ref", &"<callback>")
+            .field("subscribers", &self.subscribers.borrow().len())
+            .field("raw_subscribers", &self.raw_subscribers.borrow().len())
+            .field("debounced", &self.debounced.borrow().len())
+            .field("stable", &self.stable.borrow().len())
+            .field("notifying_stable", &self.notifying_stable.get())
+            .field("filter", &self.filter.as_ref().map(|_| "<filter>"))
+            .field("queue", &"<queue>")
+            .field("max_queue", &self.max_queue)
+            .field("buffered", &self.buffered)
+            .field("flush_on_drop", &self.flush_on_drop)
+            .field("event_budget", &self.event_budget)
+            .field("draining", &"<draining>")
+            .field("mutating", &"<mutating>")
+            .field("paused", &self.paused.get())
+            .field("detached", &self.detached.get())
+            .field("batch_depth", &self.batch_depth.get())
+            .field("tag_normalizer", &"<tag_normalizer>")
+            .field("tag_map", &self.tag_map.as_ref().map(|_| "<tag_map>"))
+            .field("tag_coalescing", &self.tag_coalescing)
+            .field("tag_stats", &self.tag_stats.borrow())
+            .field("pre_hook", &"<pre_hook>")
+            .field("post_hook", &"<post_hook>")
+            .field("extensions", &self.extensions.borrow().len())
+            .field("seq", &self.seq.get())
+            .field("writes", &self.writes.get())
+            .field("comparator", &self.comparator.as_ref().map(|_| "<comparator>"))
+            .field("inspector", &self.inspector.as_ref().map(|_| "<inspector>"))
+            .field("clock", &self.clock.as_ref().map(|_| "<clock>"))
+            .field("debounce", &self.debounce)
+            .field("pending", &self.pending.borrow().as_ref().map(|(_, due)| due))
+            .field("version", &self.version.as_ref().map(|_| "<version>"))
+            .field("trust_version", &self.trust_version)
+            .field("lazy_eq", &self.lazy_eq)
+            .field("recent", &self.recent.as_ref().map(|(window, applies, _)| (window, applies)))
+            .field("invariant", &self.invariant.as_ref().map(|(_, on_violation)| on_violation))
+            .field("monotonic", &self.monotonic.as_ref().map(|_| "<monotonic>"))
+            .field("history_cap", &self.history_cap)
+            .field("history", &self.history.borrow().len())
+            .field("checkpoints", &self.checkpoints.borrow().len())
+            .field("next_checkpoint", &self.next_checkpoint.get())
+            .field("val_cache", &self.val_cache.borrow().as_ref().map(|(seq, _)| *seq))
+            .finish()
+    }
 }
 
-impl<T: Clone + PartialEq> Mutate<T> {
-    fn new(old: T, new: T, tag: Option<String>) -> Self
-    {
-        Self { old, new, tag }
-    }
-}
+/// Primary implementation for entire mutation monitoring
+impl<T: Clone + PartialEq> OnMutate<T> {
+    /// New data being ingested
+    pub fn new<F>(value: T, callback: F) -> Self
+    where F: FnMut(&Mutate<T>) + 'static
+    {
+        Self::new_in(Rc::new(RefCell::new(value)), Box::new(callback))
+    }
+
+    /// Observe a value that's already shared elsewhere as `Rc<RefCell<T>>`
+    ///
+    /// Mutations through this monitor read and write the shared cell, and `get_val()` reflects external writes too. External writes that bypass the monitor don't emit events on their own; call `poll_external()` to pick them up
+    ///
+    pub fn from_shared<F>(shared: Rc<RefCell<T>>, callback: F) -> Self
+    where F: FnMut(&Mutate<T>) + 'static
+    {
+        let baseline = shared.borrow().clone();
+        let mut monitor = Self::new_in(shared, Box::new(callback));
+        monitor.external_baseline = Some(RefCell::new(baseline));
+        monitor
+    }
+
+    /// New data being ingested, with a context `C` owned by the monitor and handed to the callback mutably on every event
+    ///
+    /// Saves capturing `Rc<RefCell<...>>` just to accumulate state; read it back with `with_ctx()`
+    ///
+    pub fn new_with_ctx<C, F>(value: T, ctx: C, mut callback: F) -> Self
+    where
+        C: 'static,
+        F: FnMut(&mut C, &Mutate<T>) + 'static,
+    {
+        let slot = Rc::new(ContextSlot(RefCell::new(ctx)));
+        let ctx = slot.clone();
+        let monitor = Self::new(value, move |evt| callback(&mut ctx.0.borrow_mut(), evt));
+
+        monitor.extensions.borrow_mut().insert(TypeId::of::<ContextSlot<C>>(), slot);
+        monitor
+    }
+
+    /// Read the context given to `new_with_ctx()` (not from inside the callback, which already has it)
+    ///
+    /// Panics if the monitor wasn't created with a context of type `C`
+    ///
+    pub fn with_ctx<C: 'static, R>(&self, f: impl FnOnce(&C) -> R) -> R {
+        let slot = self.find_extension::<ContextSlot<C>>()
+            .expect("monitor has no context of this type; create it with `new_with_ctx()`");
+
+        let ctx = slot.0.borrow();
+        f(&ctx)
+    }
+
+    /// Start an independent monitor from a clone of the current value, with its own callback and none of this one's subscribers or settings
+    pub fn fork<F>(&self, callback: F) -> OnMutate<T>
+    where F: FnMut(&Mutate<T>) + 'static
+    {
+        Self::new(self.get_val(), callback)
+    }
+
+    /// Move the monitor behind an `Rc`, so it can be handed to other owners
+    pub fn into_shared(self) -> Rc<Self> {
+        Rc::new(self)
+    }
+
+    /// Build a shared monitor whose callback gets the monitor itself, for callbacks that mutate it again (no `Rc<RefCell<Option<_>>>` holder needed)
+    ///
+    /// The callback only holds a weak reference, so it doesn't keep the monitor alive; it's skipped for events drained as the monitor drops
+    ///
+    pub fn new_shared<F>(value: T, mut callback: F) -> Rc<Self>
+    where
+        T: 'static,
+        F: FnMut(&OnMutate<T>, &Mutate<T>) + 'static,
+    {
+        Rc::new_cyclic(|weak: &Weak<Self>| {
+            let weak = weak.clone();
+
+            Self::new(value, move |evt| {
+                if let Some(on) = weak.upgrade() {
+                    callback(&on, evt);
+                }
+            })
+        })
+    }
+
+    fn new_in(mut_value: Rc<RefCell<T>>, callback: Callback<T>) -> Self {
+        Self {
+            mut_value,
+            external_baseline: None,
+            callback_ref: RefCell::new(Some(callback)),
+            subscribers: RefCell::new(Vec::new()),
+            raw_subscribers: RefCell::new(Vec::new()),
+            debounced: RefCell::new(Vec::new()),
+            stable: RefCell::new(Vec::new()),
+            notifying_stable: Cell::new(false),
+            next_id: Cell::new(0),
+            filter: None,
+            queue: RefCell::new(VecDeque::new()),
+            max_queue: None,
+            buffered: false,
+            flush_on_drop: false,
+            event_budget: None,
+            draining: Cell::new(false),
+            mutating: Cell::new(false),
+            paused: Cell::new(false),
+            detached: Cell::new(false),
+            batch_depth: Cell::new(0),
+            tag_normalizer: None,
+            tag_map: None,
+            tag_coalescing: false,
+            tag_stats: RefCell::new(HashMap::new()),
+            pre_hook: RefCell::new(None),
+            post_hook: RefCell::new(None),
+            extensions: RefCell::new(HashMap::new()),
+            seq: Rc::new(Cell::new(0)),
+            writes: Rc::new(Cell::new(0)),
+            comparator: None,
+            inspector: None,
+            clock: None,
+            debounce: None,
+            pending: RefCell::new(None),
+            version: None,
+            trust_version: false,
+            lazy_eq: false,
+            recent: None,
+            invariant: None,
+            monotonic: None,
+            history_cap: None,
+            history: RefCell::new(VecDeque::new()),
+            checkpoints: RefCell::new(HashMap::new()),
+            next_checkpoint: Cell::new(0),
+            val_cache: RefCell::new(None),
+        }
+    }
+
+    /// Normalize every tag (e.g. trim and lowercase) before it's stored on an event; untagged events are unaffected
+    pub fn with_tag_normalizer(mut self, f: impl Fn(&str) -> String + 'static) -> Self {
+        self.tag_normalizer = Some(Box::new(f));
+        self
+    }
+
+    /// Rewrite each event's tag right before delivery (e.g. to redact or strip tags); unlike the normalizer, queued events and history keep the original
+    pub fn with_tag_map(mut self, f: impl Fn(Option<String>) -> Option<String> + 'static) -> Self {
+        self.tag_map = Some(Box::new(f));
+        self
+    }
+
+    /// Merge each run of adjacent queued events sharing a tag into their net change before delivery; untagged events and tag changes break the run
+    ///
+    /// Runs are merged within each drained batch; a merged run is still delivered if it ends where it started
+    ///
+    pub fn with_tag_coalescing(mut self) -> Self {
+        self.tag_coalescing = true;
+        self
+    }
+
+    /// Only deliver events `pred` accepts, to the callback and every subscriber; rejected events are still drained (see `subscribe_raw()`)
+    pub fn with_filter(mut self, pred: impl Fn(&Mutate<T>) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(pred));
+        self
+    }
+
+    /// Queue events without delivering them until `drain_now()` is called
+    pub fn buffered(mut self) -> Self {
+        self.buffered = true;
+        self
+    }
+
+    /// Deliver any events still queued when the monitor drops, instead of discarding them (e.g. a buffered monitor torn down at shutdown)
+    pub fn with_flush_on_drop(mut self) -> Self {
+        self.flush_on_drop = true;
+        self
+    }
+
+    /// Cap the queue at `max` events, applying `policy` once it's full
+    pub fn with_max_queue(mut self, max: usize, policy: OverflowPolicy) -> Self {
+        self.max_queue = Some((max, policy));
+        self
+    }
+
+    /// Decide equality with `eq` instead of `PartialEq`; values `eq` considers equal don't emit events
+    pub fn with_comparator(mut self, eq: impl Fn(&T, &T) -> bool + 'static) -> Self {
+        self.comparator = Some(Box::new(eq));
+        self
+    }
+
+    /// Debugging aid: `f` is called with `"attempt"`, `"changed"`, `"suppressed"` (no event was due), `"invariant violated"` (see `Violation::Log`) and `"drained"` as mutations go through the monitor
+    pub fn with_inspect(mut self, f: impl Fn(&str) + 'static) -> Self {
+        self.inspector = Some(Box::new(f));
+        self
+    }
+
+    /// Compare `version(old)` and `version(new)` first; equal versions count as unchanged without a full comparison
+    pub fn with_version(mut self, version: impl Fn(&T) -> u64 + 'static) -> Self {
+        self.version = Some(Box::new(version));
+        self
+    }
+
+    /// Defer `replace()`'s equality check to drain time, out of the mutation path (for values with expensive `PartialEq`)
+    ///
+    /// The tradeoff: every replace swaps the value in and counts as a change (`current_seq()`, `history()`, the queue) before it's known to be one; events that turn out equal are dropped before reaching the callback or any subscriber
+    ///
+    pub fn with_lazy_eq(mut self) -> Self {
+        self.lazy_eq = true;
+        self
+    }
+
+    /// Ignore a replace (`replace()`, `replace_many()`, `replace_cow()`, ...) whose value equals one of the last `window` distinct values changed to: no event and no change, which stops oscillation between a few states
+    ///
+    /// The window starts empty, so the first full cycle (e.g. `0 -> 1 -> 0`) is delivered and only later flips are suppressed
+    ///
+    pub fn with_recent_dedup(mut self, window: usize) -> Self {
+        self.recent = Some((window, false, RefCell::new(VecDeque::new())));
+        self
+    }
+
+    /// `with_recent_dedup()`, except a suppressed replace still stores its value (like a `pause_during()` change): only the event is dropped
+    pub fn with_recent_dedup_applied(mut self, window: usize) -> Self {
+        self.recent = Some((window, true, RefCell::new(VecDeque::new())));
+        self
+    }
+
+    /// Check `invariant` against the new value of every change, applying `on_violation` when it doesn't hold
+    pub fn with_invariant(mut self, invariant: impl Fn(&T) -> bool + 'static, on_violation: Violation<T>) -> Self {
+        self.invariant = Some((Box::new(invariant), on_violation));
+        self
+    }
+
+    /// Watch a key that should only move forward (timestamps, sequence numbers, ...): a change that lowers `key` calls `on_regression(old_key, new_key)` as it's queued
+    ///
+    /// The change applies unless the handler returns `true`, which rejects it like `Violation::Rollback` (no event, value restored)
+    ///
+    pub fn with_monotonic(
+        mut self,
+        key: impl Fn(&T) -> u64 + 'static,
+        on_regression: impl FnMut(u64, u64) -> bool + 'static,
+    ) -> Self {
+        self.monotonic = Some((Box::new(key), RefCell::new(Some(Box::new(on_regression)))));
+        self
+    }
+
+    /// With `with_version()`, treat differing versions as a change outright (only sound if every real change bumps the version)
+    pub fn trust_version(mut self, trust: bool) -> Self {
+        self.trust_version = trust;
+        self
+    }
+
+    /// Keep the last `cap` events (as queued) for `history()`
+    pub fn with_history(mut self, cap: usize) -> Self {
+        self.history_cap = Some(cap);
+        self
+    }
+
+    /// Also evict history entries queued more than `max_age` before the newest one; without `with_history()`, history is then bounded by age alone
+    #[cfg(feature = "timestamps")]
+    pub fn with_history_max_age(mut self, max_age: Duration) -> Self {
+        self.history_cap.get_or_insert(usize::MAX);
+        self.extension(|| HistoryMaxAge(Cell::new(max_age))).0.set(max_age);
+        self
+    }
+
+    /// Read time from `now` instead of `Instant::now` (e.g. a fake clock in tests)
+    pub fn with_clock(mut self, now: impl Fn() -> Instant + 'static) -> Self {
+        self.clock = Some(Box::new(now));
+        self
+    }
+
+    /// Make `replace_debounced()` hold values until `delay` has passed without another one arriving
+    pub fn with_debounce(mut self, delay: Duration) -> Self {
+        self.debounce = Some(delay);
+        self
+    }
+
+    /// Limit each automatic drain to `max_per_drain` events; the rest stay queued for the next drain (or `drain_now()`)
+    pub fn with_event_budget(mut self, max_per_drain: usize) -> Self {
+        self.event_budget = Some(max_per_drain);
+        self
+    }
+
+    /// Deliver every queued event now, ignoring any event budget (the only way events leave a buffered monitor)
+    pub fn drain_now(&self) {
+        self.drain_queue(None);
+    }
+
+    /// Detach the callback; until `restore_callback()`, events stay queued instead of being delivered (`drain_now()` still delivers them, to subscribers only)
+    ///
+    /// Returns `None` if there's no callback to take, including while a drain is running it (i.e. from inside the callback)
+    ///
+    pub fn take_callback(&self) -> Option<Callback<T>> {
+        let callback = self.callback_ref.borrow_mut().take()?;
+        self.detached.set(true);
+        Some(callback)
+    }
+
+    /// Reattach a callback (typically one from `take_callback()`) and deliver whatever queued up meanwhile
+    pub fn restore_callback(&self, callback: Callback<T>) {
+        *self.callback_ref.borrow_mut() = Some(callback);
+        self.detached.set(false);
+        self.auto_drain();
+    }
+
+    /// Drain every queued event through `consumer` instead of the callback and subscribers (e.g. a one-off flush)
+    ///
+    /// Events queued by `consumer` itself are drained through it too. Does nothing if the queue is already draining
+    ///
+    pub fn drain_with(&self, mut consumer: impl FnMut(&Mutate<T>)) {
+        if self.draining.replace(true) {
+            return;
+        }
+
+        loop {
+            let batch = std::mem::take(&mut *self.queue.borrow_mut());
+
+            if batch.is_empty() {
+                break;
+            }
+
+            for new_event in batch {
+                consumer(&new_event);
+            }
+        }
+
+        self.draining.set(false);
+    }
+
+    /// Remove and return up to `max` events from the front of the queue without delivering them; the rest stay queued in order
+    pub fn take_events_capped(&self, max: usize) -> Vec<Mutate<T>> {
+        let mut q = self.queue.borrow_mut();
+        let n = max.min(q.len());
+        q.drain(..n).collect()
+    }
+
+    /// Run `f` with notifications paused: events from mutations inside `f` are discarded, never delivered (e.g. internal bookkeeping)
+    ///
+    /// The changes themselves still happen and still count for `current_seq()`; events queued before `f` runs are unaffected
+    ///
+    pub fn pause_during<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _scope = PauseScope::enter(&self.paused);
+        f()
+    }
+
+    /// Number of events queued and not yet delivered
+    pub fn queue_len(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Number of registered subscribers, internal listeners (streams, histograms, ...) included; for tests
+    #[doc(hidden)]
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.borrow().len()
+    }
+
+    /// Capacity currently held by the queue, which can stay large after a burst
+    pub fn queue_capacity(&self) -> usize {
+        self.queue.borrow().capacity()
+    }
+
+    /// Release the queue's spare capacity; does nothing while events are still queued
+    pub fn shrink_queue(&self) {
+        let mut q = self.queue.borrow_mut();
+
+        if q.is_empty() {
+            q.shrink_to_fit();
+        }
+    }
+
+    /// Sequence number of the latest change; starts at 0 and increases by one per change
+    pub fn current_seq(&self) -> u64 {
+        self.seq.get()
+    }
+
+    /// Has anything changed since `seq` (typically a checkpointed `current_seq()`)?
+    pub fn changed_since(&self, seq: u64) -> bool {
+        self.seq.get() > seq
+    }
+
+    /// Get the current mutated value
+    pub fn get_val(&self) -> T {
+        self.mut_value.borrow().clone()
+    }
+
+    /// Read the value in place, without cloning it; the read-only dual of `with_mut()`
+    pub fn with_ref<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.read_guard())
+    }
+
+    /// Borrow the value immutably, the read-only counterpart to `with_guard()`
+    ///
+    /// Mutating the monitor while the guard is alive panics, like any other `RefCell` borrow conflict, so keep it short-lived
+    ///
+    pub fn read_guard(&self) -> Ref<'_, T> {
+        self.mut_value.borrow()
+    }
+
+    /// Like `get_val()`, but reads between changes share one clone (e.g. several callbacks reading the value within a drain)
+    ///
+    /// The cache is dropped on every change, and on writes a comparator or `with_version()` deems unchanged. Shared values (`from_shared()`) can change behind the monitor's back, so they're cloned on every call
+    ///
+    pub fn get_val_cached(&self) -> Rc<T> {
+        if self.external_baseline.is_some() {
+            return Rc::new(self.get_val());
+        }
+
+        let writes = self.writes.get();
+        let mut cache = self.val_cache.borrow_mut();
+
+        match &*cache {
+            Some((cached_at, value)) if *cached_at == writes => value.clone(),
+            _ => {
+                let value = Rc::new(self.get_val());
+                *cache = Some((writes, value.clone()));
+                value
+            }
+        }
+    }
+
+    /// Consume the monitor and return the value; queued, undelivered events are dropped (unless `with_flush_on_drop()` is set)
+    ///
+    /// If the value is still shared (`from_shared()`, `computed()`, ...), a clone is returned instead
+    ///
+    pub fn into_inner(self) -> T {
+        let value = self.mut_value.clone();
+        drop(self);
+
+        Rc::try_unwrap(value).map_or_else(|shared| shared.borrow().clone(), RefCell::into_inner)
+    }
+
+    /// Like `into_inner()`, but refuses while events are queued, handing the monitor back with the pending count so it can be drained first
+    #[allow(clippy::result_large_err)] // The monitor keeps its state inline (no extra allocation), and handing it back by value is the point
+    pub fn try_into_inner(self) -> Result<T, (Self, usize)> {
+        match self.queue_len() {
+            0 => Ok(self.into_inner()),
+            pending => Err((self, pending)),
+        }
+    }
+
+    /// Push a new event to `queue_event`, if it actually changed
+    pub fn replace(&self, new_value: T) {
+        if self.replace_deferred(new_value, None) {
+            self.auto_drain();
+        }
+    }
+
+    /// `Cell`-style alias of `replace()`
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /// `Cell`-style functional update: `f` gets (a clone of) the current value and returns the new one; notify if different
+    pub fn update(&self, f: impl FnOnce(T) -> T) {
+        self.replace(f(self.get_val()));
+    }
+
+    /// Like `replace()`, but panics if nothing changed (an equal value, or one an invariant rolled back); meant for tests, to catch setup that silently no-ops
+    #[track_caller]
+    pub fn replace_expect_change(&self, new_value: T) {
+        assert!(self.replace_deferred(new_value, None), "replace_expect_change: nothing changed (the value was equal, or an invariant rolled it back)");
+        self.auto_drain();
+    }
+
+    /// Replace, then return a clone of what's stored; with a comparator that deems the value unchanged, that's the existing value
+    pub fn replace_and_get(&self, new_value: T) -> T {
+        let changed = self.replace_deferred(new_value, None);
+
+        // Read before draining, so callbacks mutating the value don't leak into the result
+        let stored = self.get_val();
+
+        if changed {
+            self.auto_drain();
+        }
+
+        stored
+    }
+
+    /// Replace only if `version_of(&new_value)` is newer than the current value's, ignoring stale (out-of-order) updates; returns whether it was accepted
+    ///
+    /// An accepted value that compares equal to the current one still emits nothing
+    ///
+    pub fn replace_if_newer(&self, new_value: T, version_of: impl Fn(&T) -> u64) -> bool {
+        if version_of(&new_value) <= version_of(&self.mut_value.borrow()) {
+            return false;
+        }
+
+        self.replace(new_value);
+        true
+    }
+
+    /// Replace only if `invariant(&new_value)` holds; otherwise hand the value back in the error without touching the monitor
+    pub fn replace_checked(&self, new_value: T, invariant: impl Fn(&T) -> bool) -> Result<(), InvariantError<T>> {
+        if !invariant(&new_value) {
+            return Err(InvariantError { rejected: new_value });
+        }
+
+        self.replace(new_value);
+        Ok(())
+    }
+
+    /// Replace and always emit, even if the value is equal (e.g. to re-broadcast the current state); the event is `EventKind::Forced`
+    pub fn replace_forced(&self, new_value: T) {
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        let old = self.mut_value.replace(new_value.clone());
+
+        // Release before pushing to queue
+        drop(scope);
+        self.run_hook(&self.post_hook);
+
+        self.queue_event(Mutate::forced(old, new_value, None));
+    }
+
+    /// Replace with a possibly-borrowed value, which is only made owned (cloned) if it's actually a change
+    pub fn replace_cow(&self, new_value: Cow<'_, T>) {
+        if self.replace_deferred_cow(new_value, None) {
+            self.auto_drain();
+        }
+    }
+
+    /// Replace with each value in turn, draining once at the end; returns how many replacements were actual changes
+    pub fn replace_many(&self, values: impl IntoIterator<Item = T>) -> usize {
+        let mut changes = 0;
+
+        for value in values {
+            if self.replace_deferred(value, None) {
+                changes += 1;
+            }
+        }
+
+        self.auto_drain();
+        changes
+    }
+
+    /// Replace once the debounce delay has passed quietly; returns whether the value was applied right away
+    ///
+    /// A deferred value overwrites any earlier pending one and restarts the delay; `poll()` applies it once it's due. Without `with_debounce()`, this is just `replace()`
+    ///
+    pub fn replace_debounced(&self, value: T) -> bool {
+        let Some(delay) = self.debounce else {
+            self.replace(value);
+            return true;
+        };
+
+        *self.pending.borrow_mut() = Some((value, self.now() + delay));
+        false
+    }
+
+    /// Apply the pending debounced value and flush `subscribe_debounced()` subscribers whose delay has passed; returns whether anything did
+    pub fn poll(&self) -> bool {
+        let now = self.now();
+        let due = self.pending.borrow().as_ref().is_some_and(|(_, at)| *at <= now);
+
+        if due {
+            if let Some((value, _)) = self.pending.borrow_mut().take() {
+                self.replace(value);
+            }
+        }
+
+        self.flush_debounced(now) || due
+    }
+
+    /// Is a debounced value waiting on `poll()`?
+    pub fn has_pending(&self) -> bool {
+        self.pending.borrow().is_some()
+    }
+
+    /// Apply every value currently waiting in `rx` (without blocking), as `replace_many()` would; returns how many were actual changes
+    pub fn pump_from(&self, rx: &mpsc::Receiver<T>) -> usize {
+        self.replace_many(rx.try_iter())
+    }
+
+    /// Reset to `T::default()`, tagged `"reset"`; nothing fires if the value is already the default
+    pub fn reset_to_default(&self)
+    where T: Default
+    {
+        if self.replace_deferred(T::default(), Some("reset".into())) {
+            self.auto_drain();
+        }
+    }
+
+    /// Replace semantics without draining; returns whether an event was queued
+    fn replace_deferred(&self, new_value: T, tag: Option<String>) -> bool {
+        self.replace_deferred_cow(Cow::Owned(new_value), tag)
+    }
+
+    /// `replace_deferred()` for a possibly-borrowed value, only made owned once we know it's a change
+    fn replace_deferred_cow(&self, new_value: Cow<'_, T>, tag: Option<String>) -> bool {
+        if self.is_recent(&new_value) {
+            self.inspect("suppressed");
+
+            if self.recent.as_ref().is_some_and(|(_, applies, _)| *applies) {
+                let _scope = PauseScope::enter(&self.paused);
+                self.replace_unchecked_cow(new_value, tag);
+            }
+
+            return false;
+        }
+
+        self.replace_unchecked_cow(new_value, tag)
+    }
+
+    /// `replace_deferred_cow()` without the `with_recent_dedup()` check
+    fn replace_unchecked_cow(&self, new_value: Cow<'_, T>, tag: Option<String>) -> bool {
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        let mut current = self.mut_value.borrow_mut();
+        let new_event = (self.lazy_eq || self.differs(&current, &new_value)).then(|| {
+            let new_value = new_value.into_owned();
+            let new_event = Mutate::new(current.clone(), new_value.clone(), tag);
+            *current = new_value;
+            new_event
+        });
+
+        // Release before pushing to queue
+        drop(current);
+        drop(scope);
+        self.run_hook(&self.post_hook);
+
+        match new_event {
+            Some(new_event) => self.push_event(new_event),
+            None => {
+                self.inspect("suppressed");
+                false
+            }
+        }
+    }
+
+    /// Begin mutation detection, notify if changed. Also comes with a non-intrusive tag for categorizing
+    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        let tag = tag.into();
+        self.mutate_with(|v, _| f(v), move |_, _, _| tag)
+    }
+
+    /// Like `with_mut()`, but the tag is derived afterwards by `classify(old, new)` (e.g. "incremented" vs "decremented")
+    pub fn with_mut_auto_tag<R>(&self, classify: impl Fn(&T, &T) -> Option<String>, f: impl FnOnce(&mut T) -> R) -> R {
+        self.mutate_with(|v, _| f(v), |old, new, _| classify(old, new))
+    }
+
+    /// Mutate with a `MutationCtx`, letting the closure pick the tag, suppress the event, or force one
+    pub fn with_mut_ctx<R>(&self, f: impl FnOnce(&mut T, &mut MutationCtx) -> R) -> R {
+        self.mutate_with(f, |_, _, tag| tag)
+    }
+
+    /// Like `with_mut()`, but if `f` panics partway through, whatever it changed is still compared and delivered before the panic resumes
+    pub fn with_mut_notify_on_panic<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut guard = self.guard(tag.into());
+        let out = panic::catch_unwind(AssertUnwindSafe(|| f(&mut guard)));
+
+        // Dropping the guard queues (and delivers) the partial change outside of the unwind
+        drop(guard);
+
+        match out {
+            Ok(out) => out,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Run `f` (which may mutate through the monitor any number of times) and deliver everything it queued once, at the end
+    ///
+    /// Batches nest: only the outermost one drains
+    ///
+    pub fn batch<R>(&self, f: impl FnOnce() -> R) -> R {
+        let out = {
+            let _scope = BatchScope::enter(&self.batch_depth);
+            f()
+        };
+
+        self.auto_drain();
+        out
+    }
+
+    /// A `with_mut()` meant as one transaction: a single event for everything `f` changes, delivered once the outermost `batch()`/`transaction()` ends
+    pub fn transaction<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        self.batch(|| self.with_mut(tag, f))
+    }
+
+    /// Shared body of `with_mut()` and friends; `tag_for(old, new, ctx_tag)` only runs once we know an event is due
+    fn mutate_with<R>(
+        &self,
+        f: impl FnOnce(&mut T, &mut MutationCtx) -> R,
+        tag_for: impl FnOnce(&T, &T, Option<String>) -> Option<String>,
+    ) -> R {
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        // We clone `old` in its own scope so the immutable borrow is dropped
+        // This needs to happen before we try to take a new mutable borrow
+        let old = {
+            let b = self.mut_value.borrow();
+            b.clone()
+        };
+
+        let mut ctx = MutationCtx::default();
+        let mut borrow = self.mut_value.borrow_mut();
+        let out = f(&mut borrow, &mut ctx);
+        let changed = self.differs(&borrow, &old);
+        let unseen_write = !changed && self.loose_detection();
+        let new_snapshot = (!ctx.suppressed && (ctx.forced || changed)).then(|| borrow.clone());
+
+        // Release before pushing to queue (this including draining the queue if applicable)
+        drop(borrow);
+        drop(scope);
+        self.run_hook(&self.post_hook);
+
+        // A suppressed change still counts as a change (e.g. for `Computed`); a write change detection looked past may still differ from cached copies
+        if changed && ctx.suppressed {
+            self.bump_seq();
+        } else if unseen_write {
+            self.bump_writes();
+        }
+
+        // If the borrowed value is not identical to the old value (or the closure forced it), we push to the queue
+        match new_snapshot {
+            Some(new_snapshot) => {
+                let tag = tag_for(&old, &new_snapshot, ctx.tag);
+                let new_event = if changed { Mutate::new(old, new_snapshot, tag) } else { Mutate::forced(old, new_snapshot, tag) };
+                self.queue_event(new_event);
+            }
+            None => self.inspect("suppressed"),
+        }
+
+        out
+    }
+
+    /// A monitoring guard that notifies when/if a value is mutated or changed during the drop
+    pub fn with_guard(&self) -> OnMutationChange<'_, T> {
+        self.guard(None)
+    }
+
+    /// Same as `with_guard()`, but returns `BorrowMutError` instead of panicking if the value is already borrowed
+    pub fn try_with_guard(&self) -> Result<OnMutationChange<'_, T>, BorrowMutError> {
+        // Probe the borrow first, so the pre-hook never runs for a guard we can't hand out
+        drop(self.mut_value.try_borrow_mut()?);
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        let borrow = self.mut_value.borrow_mut();
+        let old = borrow.clone();
+
+        Ok(OnMutationChange {
+            owner: self,
+            old,
+            borrow: Some(borrow),
+            tag: None,
+            scope: Some(scope),
+            on_drop: None,
+            checkpointed: false,
+        })
+    }
+
+    /// A self-contained function for including a tag (outside of `with_mut()`)
+    pub fn with_tag(&self, tag: impl Into<String>) -> OnMutationChange<'_, T> {
+        self.guard(Some(tag.into()))
+    }
+
+    /// Scope a guard to a closure; the guard drops (and notifies) once `f` returns
+    pub fn with_guard_scope<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut OnMutationChange<'_, T>) -> R) -> R {
+        let mut guard = self.guard(tag.into());
+        f(&mut guard)
+    }
+
+    /// Shared constructor for `with_guard()` and `with_tag()`
+    fn guard(&self, tag: Option<String>) -> OnMutationChange<'_, T> {
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        // We clone "old" in its own scope so the immutable borrow is dropped
+        let old = {
+            let b = self.mut_value.borrow();
+            b.clone()
+        };
+
+        OnMutationChange {
+            owner: self,
+            old,
+            borrow: Some(self.mut_value.borrow_mut()),
+            tag,
+            scope: Some(scope),
+            on_drop: None,
+            checkpointed: false,
+        }
+    }
+
+    /// Called with the current value right before every mutation attempt, even ones that end up changing nothing
+    pub fn set_pre_hook(&self, f: impl FnMut(&T) + 'static) {
+        *self.pre_hook.borrow_mut() = Some(Box::new(f));
+    }
+
+    /// Called with the current value right after every mutation attempt (before any event is delivered)
+    pub fn set_post_hook(&self, f: impl FnMut(&T) + 'static) {
+        *self.post_hook.borrow_mut() = Some(Box::new(f));
+    }
+
+    /// Run a hook with the value borrowed immutably; the hook is taken out of its slot so it can replace itself
+    fn run_hook(&self, slot: &RefCell<Option<Hook<T>>>) {
+        let Some(mut hook) = slot.borrow_mut().take() else { return };
+        hook(&self.mut_value.borrow());
+
+        let mut slot = slot.borrow_mut();
+
+        if slot.is_none() {
+            *slot = Some(hook);
+        }
+    }
+
+    /// Change detection: versions first (if set), then the custom comparator if one is set, `PartialEq` otherwise
+    fn differs(&self, a: &T, b: &T) -> bool {
+        if let Some(version) = &self.version {
+            if version(a) == version(b) {
+                return false;
+            }
+
+            if self.trust_version {
+                return true;
+            }
+        }
+
+        match &self.comparator {
+            Some(eq) => !eq(a, b),
+            None => a != b,
+        }
+    }
+
+    /// Record that the value changed, whether or not an event makes it to the queue
+    fn bump_seq(&self) {
+        self.seq.set(self.seq.get() + 1);
+        self.bump_writes();
+    }
+
+    /// Can change detection call values unchanged that `PartialEq` would tell apart (a comparator or version)? Comparing again would defeat the point, so such writes always invalidate caches
+    fn loose_detection(&self) -> bool {
+        self.comparator.is_some() || self.version.is_some()
+    }
+
+    /// Invalidate caches keyed on the value (`get_val_cached()`, `Computed`) without counting a change
+    fn bump_writes(&self) {
+        self.writes.set(self.writes.get() + 1);
+    }
+
+    /// Typed per-monitor state (keyed by its type), created with `init` on first use
+    fn extension<X: Any>(&self, init: impl FnOnce() -> X) -> Rc<X> {
+        let ext = self.extensions.borrow_mut()
+            .entry(TypeId::of::<X>())
+            .or_insert_with(|| Rc::new(init()))
+            .clone();
+
+        ext.downcast::<X>().unwrap_or_else(|_| unreachable!("extensions are keyed by their own type"))
+    }
+
+    /// Typed per-monitor state, if it was ever created
+    fn find_extension<X: Any>(&self) -> Option<Rc<X>> {
+        let ext = self.extensions.borrow().get(&TypeId::of::<X>()).cloned()?;
+        ext.downcast::<X>().ok()
+    }
+
+    /// Flag the monitor as mid-mutation, so nested mutations fail loudly instead of with a `BorrowMutError`
+    fn enter_mutation(&self) -> MutationScope<'_> {
+        if self.mutating.replace(true) {
+            panic!("nested mutation on the same OnMutate is not allowed; queue the change instead");
+        }
+
+        self.inspect("attempt");
+
+        MutationScope(&self.mutating)
+    }
+
+    /// Current time from the configured clock
+    fn now(&self) -> Instant {
+        self.clock.as_ref().map_or_else(Instant::now, |now| now())
+    }
+
+    /// Report a lifecycle label to the inspector, if any
+    fn inspect(&self, label: &str) {
+        if let Some(f) = &self.inspector {
+            f(label);
+        }
+    }
+
+    /// Emit an event if a shared value was changed outside this monitor since it last looked; returns whether it did
+    pub fn poll_external(&self) -> bool {
+        let Some(baseline) = &self.external_baseline else { return false };
+        let current = self.mut_value.borrow().clone();
+
+        if !self.differs(&baseline.borrow(), &current) {
+            return false;
+        }
+
+        let old = baseline.replace(current.clone());
+        self.queue_event(Mutate::new(old, current, None));
+        true
+    }
+
+    /// Snapshot the current value, for asking `diff_since()` what changed later
+    pub fn checkpoint(&self) -> CheckpointId {
+        let id = CheckpointId(self.next_checkpoint.get());
+        self.next_checkpoint.set(id.0 + 1);
+        self.checkpoints.borrow_mut().insert(id, self.get_val());
+        id
+    }
+
+    /// Net change from the checkpoint's snapshot to the current value; `None` if they're equal or the checkpoint is unknown
+    pub fn diff_since(&self, id: CheckpointId) -> Option<Mutate<T>> {
+        let checkpoints = self.checkpoints.borrow();
+        let old = checkpoints.get(&id)?;
+        let current = self.mut_value.borrow();
+
+        self.differs(old, &current).then(|| Mutate::new(old.clone(), current.clone(), None))
+    }
+
+    /// Drop a checkpoint's snapshot; returns `false` if it was already gone
+    pub fn release_checkpoint(&self, id: CheckpointId) -> bool {
+        self.checkpoints.borrow_mut().remove(&id).is_some()
+    }
+
+    /// Recorded events, oldest first (empty unless `with_history()` is set)
+    pub fn history(&self) -> Vec<Mutate<T>> {
+        self.history.borrow().iter().map(|entry| entry.event.clone()).collect()
+    }
+
+    /// Forget every recorded event
+    pub fn clear_history(&self) {
+        self.history.borrow_mut().clear();
+    }
+
+    /// Recorded events queued in `start..end`: `start` is inclusive and `end` exclusive, so `start >= end` returns nothing
+    #[cfg(feature = "timestamps")]
+    pub fn events_between(&self, start: Instant, end: Instant) -> Vec<Mutate<T>> {
+        self.history.borrow().iter()
+            .filter(|entry| entry.at >= start && entry.at < end)
+            .map(|entry| entry.event.clone())
+            .collect()
+    }
+
+    /// Recorded events per second over the trailing `window` (up to and including now); 0 for an empty window
+    ///
+    /// Only events still in the history count, so the history cap should comfortably cover the window
+    ///
+    #[cfg(feature = "timestamps")]
+    pub fn change_rate(&self, window: Duration) -> f64 {
+        if window.is_zero() {
+            return 0.0;
+        }
+
+        let now = self.now();
+        let since = now.checked_sub(window);
+        let count = self.history.borrow().iter()
+            .filter(|entry| entry.at <= now && since.is_none_or(|since| entry.at > since))
+            .count();
+
+        count as f64 / window.as_secs_f64()
+    }
+
+    /// How long ago the latest event was queued (by the configured clock); `None` if nothing has changed yet
+    #[cfg(feature = "timestamps")]
+    pub fn time_since_last_change(&self) -> Option<Duration> {
+        let at = self.find_extension::<LastChange>()?.0.get()?;
+        Some(self.now().saturating_duration_since(at))
+    }
+
+    /// Append to the history, evicting the oldest entries past the cap
+    fn record_history(&self, event: &Mutate<T>) {
+        let Some(cap) = self.history_cap.filter(|cap| *cap > 0) else { return };
+        let mut history = self.history.borrow_mut();
+
+        while history.len() >= cap {
+            history.pop_front();
+        }
+
+        #[cfg(feature = "timestamps")]
+        let at = self.now();
+
+        #[cfg(feature = "timestamps")]
+        if let Some(max_age) = self.find_extension::<HistoryMaxAge>() {
+            while history.front().is_some_and(|entry| at.saturating_duration_since(entry.at) > max_age.0.get()) {
+                history.pop_front();
+            }
+        }
+
+        history.push_back(HistoryEntry {
+            event: event.clone(),
+            #[cfg(feature = "timestamps")]
+            at,
+        });
+    }
+
+    /// How many events have been delivered under each tag; untagged events are counted under `UNTAGGED`
+    pub fn tag_counts(&self) -> HashMap<String, u64> {
+        self.tag_stats.borrow().clone()
+    }
+
+    /// Forget every tag count
+    pub fn reset_tag_stats(&self) {
+        self.tag_stats.borrow_mut().clear();
+    }
+
+    /// Subscribe an additional callback to every delivered event
+    pub fn subscribe(&self, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionId {
+        self.add_subscriber(0, false, None, Box::new(cb))
+    }
+
+    /// Like `subscribe()`, but the subscription lasts only as long as the returned guard
+    pub fn subscribe_scoped(&self, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionGuard<'_, T> {
+        SubscriptionGuard { owner: self, id: self.subscribe(cb) }
+    }
+
+    /// Subscribe a callback that's already boxed (e.g. assembled at runtime), without boxing it again
+    pub fn subscribe_boxed(&self, cb: Callback<T>) -> SubscriptionId {
+        self.add_subscriber(0, false, None, cb)
+    }
+
+    /// Subscribe a callback that fires on the first event delivered after registration, then removes itself
+    pub fn subscribe_first(&self, cb: impl FnOnce(&Mutate<T>) + 'static) -> SubscriptionId {
+        let mut cb = Some(cb);
+
+        self.add_subscriber(0, true, None, Box::new(move |evt| {
+            if let Some(cb) = cb.take() {
+                cb(evt);
+            }
+        }))
+    }
+
+    /// Subscribe with debounced delivery: bursts are collapsed into their net change, delivered by `poll()` once `delay` passes without another event
+    ///
+    /// Other subscribers keep getting every event right away
+    ///
+    pub fn subscribe_debounced(&self, delay: Duration, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+
+        self.debounced.borrow_mut().push(Debounced {
+            id,
+            delay,
+            pending: None,
+            callback: Some(Box::new(cb)),
+        });
+
+        id
+    }
+
+    /// Subscribe a callback that gets the settled value once a drain (re-entrant cascades included) has emptied the queue
+    ///
+    /// It's skipped if the value is equal to the last one it got, so a cascade that ends where it started doesn't fire
+    ///
+    pub fn subscribe_stable(&self, cb: impl FnMut(&T) + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+
+        self.stable.borrow_mut().push(Stable { id, last: None, callback: Some(Box::new(cb)) });
+        id
+    }
+
+    /// Subscribe with an explicit priority; higher priorities are notified first, equal ones in subscription order
+    pub fn subscribe_with_priority(&self, priority: i32, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionId {
+        self.add_subscriber(priority, false, None, Box::new(cb))
+    }
+
+    /// Subscribe a callback that only receives the events `pred` accepts
+    pub fn subscribe_filtered(
+        &self,
+        pred: impl Fn(&Mutate<T>) -> bool + 'static,
+        cb: impl FnMut(&Mutate<T>) + 'static,
+    ) -> SubscriptionId {
+        self.add_subscriber(0, false, Some(Box::new(pred)), Box::new(cb))
+    }
+
+    /// Subscribe a callback that only receives events tagged exactly `tag` (as delivered, i.e. after any `with_tag_map()`)
+    pub fn subscribe_tag(&self, tag: impl Into<String>, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionId {
+        let tag = tag.into();
+        self.subscribe_filtered(move |evt| evt.tag.as_deref() == Some(tag.as_str()), cb)
+    }
+
+    /// Subscribe to just the new value of every change
+    pub fn subscribe_values(&self, mut cb: impl FnMut(&T) + 'static) -> SubscriptionId {
+        self.subscribe(move |evt| cb(&evt.new))
+    }
+
+    /// Subscribe a callback that only fires when a mutation results in `new == target`
+    pub fn subscribe_on_value(&self, target: T, cb: impl FnMut(&Mutate<T>) + 'static) -> SubscriptionId
+    where T: 'static
+    {
+        self.subscribe_filtered(move |evt| evt.new == target, cb)
+    }
+
+    /// Diagnostics: see every drained event, with whether it passed `with_filter()` and reached the callback and subscribers
+    pub fn subscribe_raw(&self, cb: impl FnMut(&Mutate<T>, Delivered) + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.raw_subscribers.borrow_mut().push((id, Box::new(cb)));
+        id
+    }
+
+    /// Remove a subscriber (raw ones included); returns `false` if it was already gone
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers.borrow_mut();
+        let before = subscribers.len();
+        subscribers.retain(|sub| sub.id != id);
+
+        let mut raw = self.raw_subscribers.borrow_mut();
+        let raw_before = raw.len();
+        raw.retain(|(raw_id, _)| *raw_id != id);
+
+        let mut debounced = self.debounced.borrow_mut();
+        let debounced_before = debounced.len();
+        debounced.retain(|sub| sub.id != id);
+
+        let mut stable = self.stable.borrow_mut();
+        let stable_before = stable.len();
+        stable.retain(|sub| sub.id != id);
+
+        subscribers.len() != before
+            || raw.len() != raw_before
+            || debounced.len() != debounced_before
+            || stable.len() != stable_before
+    }
+
+    /// Fold a delivered event into every debounced subscriber's pending change, restarting its delay
+    fn stash_debounced(&self, new_event: &Mutate<T>) {
+        let mut debounced = self.debounced.borrow_mut();
+
+        if debounced.is_empty() {
+            return;
+        }
+
+        let now = self.now();
+
+        for sub in debounced.iter_mut() {
+            let pending = match sub.pending.take() {
+                Some((mut pending, _)) => {
+                    pending.new = new_event.new.clone();
+                    pending.tag = new_event.tag.clone();
+                    pending.kind = new_event.kind;
+                    pending
+                }
+                None => new_event.clone(),
+            };
+
+            // A burst that ended where it started has nothing to report
+            if !pending.is_forced() && !self.differs(&pending.old, &pending.new) {
+                continue;
+            }
+
+            sub.pending = Some((pending, now + sub.delay));
+        }
+    }
+
+    /// Deliver every debounced subscriber's pending change that's due at `now`; returns whether any were
+    fn flush_debounced(&self, now: Instant) -> bool {
+        let ids: Vec<SubscriptionId> = self.debounced.borrow().iter()
+            .filter(|sub| sub.pending.as_ref().is_some_and(|(_, at)| *at <= now))
+            .map(|sub| sub.id)
+            .collect();
+
+        for &id in &ids {
+            // Take the callback out so it can mutate or (un)subscribe without hitting a held borrow
+            let taken = {
+                let mut debounced = self.debounced.borrow_mut();
+                debounced.iter_mut().find(|sub| sub.id == id)
+                    .and_then(|sub| Some((sub.pending.take()?.0, sub.callback.take()?)))
+            };
+
+            let Some((new_event, mut callback)) = taken else { continue };
+            callback(&new_event);
+
+            if let Some(sub) = self.debounced.borrow_mut().iter_mut().find(|sub| sub.id == id) {
+                sub.callback = Some(callback);
+            }
+        }
+
+        !ids.is_empty()
+    }
+
+    /// Notify every raw subscriber; they're taken out while running so they can subscribe more
+    fn notify_raw(&self, new_event: &Mutate<T>, delivered: Delivered) {
+        let mut running = std::mem::take(&mut *self.raw_subscribers.borrow_mut());
+
+        for (_, cb) in running.iter_mut() {
+            cb(new_event, delivered);
+        }
+
+        let mut slot = self.raw_subscribers.borrow_mut();
+        running.append(&mut slot);
+        *slot = running;
+    }
+
+    /// Register an internal listener that sees every delivered event after the main callback
+    fn add_listener(&self, listener: Callback<T>) {
+        self.add_subscriber(0, false, None, listener);
+    }
+
+    /// `add_listener()` for a listener that removes itself once it returns `false`
+    #[cfg(feature = "futures")]
+    fn add_listener_while(&self, mut listener: impl FnMut(&Mutate<T>) -> bool + 'static) {
+        let retired = Rc::new(Cell::new(false));
+        let flag = retired.clone();
+        let id = self.add_subscriber(0, false, None, Box::new(move |evt: &Mutate<T>| {
+            if !listener(evt) {
+                flag.set(true);
+            }
+        }));
+
+        if let Some(sub) = self.subscribers.borrow_mut().iter_mut().find(|sub| sub.id == id) {
+            sub.retired = Some(retired);
+        }
+    }
+
+    fn add_subscriber(&self, priority: i32, once: bool, filter: Option<Filter<T>>, callback: Callback<T>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+
+        // Keep the list sorted by descending priority, after any subscriber with the same priority
+        let mut subscribers = self.subscribers.borrow_mut();
+        let at = subscribers.iter().position(|sub| sub.priority < priority).unwrap_or(subscribers.len());
+
+        subscribers.insert(at, Subscriber {
+            id,
+            priority,
+            once,
+            retired: None,
+            handler: Some(Handler { filter, callback }),
+        });
+
+        id
+    }
+
+    /// Notify every subscriber registered when the event started delivering
+    fn notify_subscribers(&self, new_event: &Mutate<T>) {
+        let ids: Vec<SubscriptionId> = self.subscribers.borrow().iter().map(|sub| sub.id).collect();
+
+        for id in ids {
+            // Take the handler out so it can (un)subscribe without hitting a held borrow
+            let handler = {
+                let mut subscribers = self.subscribers.borrow_mut();
+                subscribers.iter_mut().find(|sub| sub.id == id).and_then(|sub| sub.handler.take())
+            };
+
+            let Some(mut handler) = handler else { continue };
+
+            let fired = handler.filter.as_ref().is_none_or(|pred| pred(new_event));
+
+            if fired {
+                (handler.callback)(new_event);
+            }
+
+            // Put it back, unless it unsubscribed itself in the meantime (or was a one-shot that just fired)
+            let mut subscribers = self.subscribers.borrow_mut();
 
-/// Public observable wrapper for mutations
-///
-/// We maintain borrow checks (to avoid BorrowMutError) by draining a queue, this way we never make a call while a borrow is held
-///
-/// Full type implementation: `impl<T: Clone + PartialEq> OnMutate<T> {}`
-///
-pub struct OnMutate<T: Clone + PartialEq> {
-    mut_value: RefCell<T>, // Actual value being ingested
-    callback_ref: RefCell<Option<Box<dyn FnMut(&Mutate<T>) + 'static>>>, // Callback for the ingested value
-    queue: RefCell<Vec<Mutate<T>>>, // Simple queue for maintaing incoming data
-    draining: Cell<bool>, // Is the queue currently draining?
-}
+            if let Some(at) = subscribers.iter().position(|sub| sub.id == id) {
+                let retired = subscribers[at].retired.as_ref().is_some_and(|flag| flag.get());
 
-impl<T: Clone + PartialEq> fmt::Debug for OnMutate<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("OnMutate")
-            .field("mut_value", &"<value>")
-            .field("callback_ref", &"<callback>")
-            .field("queue", &"<queue>")
-            .field("draining", &"<draining>")
-            .finish()
+                if (fired && subscribers[at].once) || retired {
+                    subscribers.remove(at);
+                } else {
+                    subscribers[at].handler = Some(handler);
+                }
+            }
+        }
     }
-}
 
-/// Primary implementation for entire mutation monitoring
-impl<T: Clone + PartialEq> OnMutate<T> {
-    /// New data being ingested
-    pub fn new<F>(value: T, callback: F) -> Self
-    where F: FnMut(&Mutate<T>) + 'static
-    {
-        Self {
-            mut_value: RefCell::new(value),
-            callback_ref: RefCell::new(Some(Box::new(callback))),
-            queue: RefCell::new(Vec::new()),
-            draining: Cell::new(false),
+    /// Queue an event and drain if not already draining
+    fn queue_event(&self, new_event: Mutate<T>) {
+        self.push_event(new_event);
+        self.auto_drain();
+    }
+
+    /// Drain, unless this monitor is buffered (or its callback is detached)
+    fn auto_drain(&self) {
+        if self.drains_automatically() {
+            self.drain_queue(self.event_budget);
         }
     }
 
-    /// Get the current mutated value
-    pub fn get_val(&self) -> T {
-        self.mut_value.borrow().clone()
+    /// Does this monitor deliver on its own, i.e. it isn't buffered, batching or detached?
+    fn drains_automatically(&self) -> bool {
+        !self.buffered && !self.detached.get() && self.batch_depth.get() == 0
     }
 
-    /// Push a new event to `queue_event`, if it actually changed
-    pub fn replace(&self, new_value: T) {
-        let mut current = self.mut_value.borrow_mut();
+    /// Queue an event without draining; returns whether the change stands (`false` once an invariant rolled it back)
+    ///
+    /// Paused or overflowing monitors may still discard the event itself, but the change was made
+    ///
+    fn push_event(&self, mut new_event: Mutate<T>) -> bool {
+        if !self.check_invariant(&new_event) || !self.check_monotonic(&new_event) {
+            return false;
+        }
 
-        if *current != new_value {
-            let new_event = Mutate::new(current.clone(), new_value.clone(), None);
-            *current = new_value;
+        self.bump_seq();
+        self.inspect("changed");
 
-            // Release before pushing to queue (this including draining the queue if applicable)
-            drop(current);
-            self.queue_event(new_event);
+        #[cfg(feature = "timestamps")]
+        self.extension(|| LastChange(Cell::new(None))).0.set(Some(self.now()));
+
+        // Whatever the queue does with it, this is now the value we've seen
+        if let Some(baseline) = &self.external_baseline {
+            *baseline.borrow_mut() = new_event.new.clone();
         }
+
+        self.remember_recent(&new_event.new);
+
+        if self.paused.get() {
+            return true;
+        }
+
+        if let (Some(normalize), Some(tag)) = (&self.tag_normalizer, &new_event.tag) {
+            new_event.tag = Some(normalize(tag));
+        }
+
+        if let Some((max, policy)) = self.max_queue {
+            if self.queue.borrow().len() >= max {
+                match policy {
+                    OverflowPolicy::DropOldest => { self.queue.borrow_mut().pop_front(); }
+                    OverflowPolicy::DropNewest => return true,
+                    // Never deliver while a guard holds the value, or from a monitor that only delivers on request
+                    OverflowPolicy::Block if self.mutating.get() || !self.drains_automatically() => {}
+                    OverflowPolicy::Block => self.drain_queue(None),
+                }
+            }
+        }
+
+        self.record_history(&new_event);
+        self.queue.borrow_mut().push_back(new_event);
+        true
     }
 
-    /// Begin mutation detection, notify if changed. Also comes with a non-intrusive tag for categorizing
-    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
-        let tag = tag.into();
+    /// `push_event()` for a change already made in place (by `set_index_observed()` and friends), minus what needs a whole-value event; returns whether its side event should be delivered
+    ///
+    /// `undo` reverts the change if `with_invariant()` rolls it back. History, `with_monotonic()` and the queue cap only ever see whole-value events
+    ///
+    fn admit_in_place(&self, undo: impl FnOnce(&mut T)) -> bool {
+        if let Some((invariant, on_violation)) = &self.invariant {
+            let held = invariant(&self.mut_value.borrow());
 
-        // We clone `old` in its own scope so the immutable borrow is dropped
-        // This needs to happen before we try to take a new mutable borrow
-        let old = {
-            let b = self.mut_value.borrow();
-            b.clone()
-        };
+            if !held {
+                let value = self.get_val();
 
-        let mut borrow = self.mut_value.borrow_mut();
-        let out = f(&mut borrow);
-        let value_mutated = *borrow != old;
-        let new_snapshot = borrow.clone();
+                match on_violation {
+                    Violation::Panic => panic!("invariant violated by an in-place change"),
+                    Violation::Log => self.inspect("invariant violated"),
+                    Violation::Call(handler) => handler(&value),
+                    Violation::Rollback | Violation::RollbackWith(_) => {
+                        if let Violation::RollbackWith(handler) = on_violation {
+                            handler(&value);
+                        }
 
-        // Release before pushing to queue (this including draining the queue if applicable)
-        drop(borrow);
+                        undo(&mut self.mut_value.borrow_mut());
+                        self.bump_writes();
+                        return false;
+                    }
+                }
+            }
+        }
+
+        self.bump_seq();
+        self.inspect("changed");
 
-        // If the borrowed value is not identical to the old value, we push to the queue
-        if value_mutated {
-            self.queue_event(Mutate::new(old, new_snapshot, tag));
+        #[cfg(feature = "timestamps")]
+        self.extension(|| LastChange(Cell::new(None))).0.set(Some(self.now()));
+
+        if let Some(baseline) = &self.external_baseline {
+            *baseline.borrow_mut() = self.mut_value.borrow().clone();
         }
 
-        out
+        self.remember_recent(&self.mut_value.borrow());
+        !self.paused.get()
     }
 
-    /// A monitoring guard that notifies when/if a value is mutated or changed during the drop
-    pub fn with_guard(&self) -> OnMutationChange<'_, T> {
-        // We clone "old" in its own scope so the immutable borrow is dropped
-        let old = {
-            let b = self.mut_value.borrow();
-            b.clone()
-        };
+    /// Is `value` one of the values `with_recent_dedup()` remembers?
+    fn is_recent(&self, value: &T) -> bool {
+        self.recent.as_ref().is_some_and(|(_, _, recent)| recent.borrow().iter().any(|seen| !self.differs(seen, value)))
+    }
 
-        OnMutationChange {
-            owner: self,
-            old,
-            borrow: Some(self.mut_value.borrow_mut()),
-            tag: None,
+    /// Record a value we just changed to as the most recent one in `with_recent_dedup()`'s window
+    fn remember_recent(&self, value: &T) {
+        let Some((window, _, recent)) = &self.recent else { return };
+        let mut recent = recent.borrow_mut();
+
+        recent.retain(|seen| self.differs(seen, value));
+        recent.push_back(value.clone());
+
+        while recent.len() > *window {
+            recent.pop_front();
         }
     }
 
-    /// A self-contained function for including a tag (outside of `with_mut()`)
-    pub fn with_tag(&self, tag: impl Into<String>) -> OnMutationChange<'_, T> {
-        // We clone "old" in its own scope so the immutable borrow is dropped
-        let old = {
-            let b = self.mut_value.borrow();
-            b.clone()
-        };
+    /// Apply `with_invariant()` to a change that was just made; returns whether the event should still be queued
+    fn check_invariant(&self, new_event: &Mutate<T>) -> bool {
+        let Some((invariant, on_violation)) = &self.invariant else { return true };
 
-        OnMutationChange {
-            owner: self,
-            old,
-            borrow: Some(self.mut_value.borrow_mut()),
-            tag: Some(tag.into()),
+        if invariant(&new_event.new) {
+            return true;
+        }
+
+        match on_violation {
+            Violation::Panic => panic!("invariant violated by change tagged {:?}", new_event.tag),
+            Violation::Log => self.inspect("invariant violated"),
+            Violation::Call(handler) => handler(&new_event.new),
+            Violation::Rollback | Violation::RollbackWith(_) => {
+                if let Violation::RollbackWith(handler) = on_violation {
+                    handler(&new_event.new);
+                }
+
+                return !self.roll_back(new_event);
+            }
         }
+
+        true
     }
 
-    /// Queue an event and drain if not already draining
-    fn queue_event(&self, new_event: Mutate<T>) {
-        self.queue.borrow_mut().push(new_event);
-        self.drain_queue();
+    /// Apply `with_monotonic()` to a change that was just made; returns whether the event should still be queued
+    fn check_monotonic(&self, new_event: &Mutate<T>) -> bool {
+        let Some((key, handler)) = &self.monotonic else { return true };
+        let (old, new) = (key(&new_event.old), key(&new_event.new));
+
+        if new >= old {
+            return true;
+        }
+
+        // Take the handler out, in case it touches the monitor
+        let Some(mut on_regression) = handler.borrow_mut().take() else { return true };
+        let reject = on_regression(old, new);
+        *handler.borrow_mut() = Some(on_regression);
+
+        !(reject && self.roll_back(new_event))
+    }
+
+    /// Restore the value from before `new_event`; returns whether that was possible
+    fn roll_back(&self, new_event: &Mutate<T>) -> bool {
+        // A guard mid-`checkpoint()` still holds the value
+        let Ok(mut current) = self.mut_value.try_borrow_mut() else { return false };
+
+        *current = new_event.old.clone();
+        self.bump_writes();
+        true
     }
 
     /// Drain queued events without maintaining any `RefCell` borrows
-    fn drain_queue(&self) {
+    ///
+    /// With a `budget`, at most that many events are delivered (re-entrant ones included) and the rest stay queued
+    ///
+    fn drain_queue(&self, mut budget: Option<usize>) {
         // Already draining, return
         if self.draining.replace(true) {
             return;
         }
 
+        let mut drained_any = false;
+
         // We'll keep taking a snapshot of the queue and invoking without holding borrows.
         loop {
             // Construct the current batch/queue
             let batch = {
                 let mut q = self.queue.borrow_mut();
-                if q.is_empty() { break; }
-                std::mem::take(&mut *q)
+                if q.is_empty() || budget == Some(0) { break; }
+
+                match budget {
+                    Some(n) if n < q.len() => {
+                        let rest = q.split_off(n);
+                        std::mem::replace(&mut *q, rest)
+                    }
+                    _ => std::mem::take(&mut *q),
+                }
             };
 
+            if let Some(n) = budget.as_mut() {
+                *n -= batch.len();
+            }
+
+            drained_any = true;
+
+            let batch = if self.tag_coalescing { coalesce_tags(batch) } else { batch };
+
             // Extract the callback references
             let mut callback_opt = {
                 let mut slot = self.callback_ref.borrow_mut();
                 slot.take()
             };
 
-            for new_event in batch {
+            for mut new_event in batch {
+                // Lazy replaces only get compared now
+                if self.lazy_eq && !new_event.is_forced() && !self.differs(&new_event.old, &new_event.new) {
+                    self.inspect("suppressed");
+                    continue;
+                }
+
+                if let Some(map) = &self.tag_map {
+                    new_event.tag = map(new_event.tag.take());
+                }
+
+                if self.filter.as_ref().is_some_and(|pred| !pred(&new_event)) {
+                    self.notify_raw(&new_event, Delivered::Filtered);
+                    continue;
+                }
+
+                let key = new_event.tag_or(UNTAGGED);
+                *self.tag_stats.borrow_mut().entry(key.to_string()).or_insert(0) += 1;
+
                 if let Some(ref mut callback_ref) = callback_opt {
                     (callback_ref)(&new_event);
                 }
+
+                self.notify_subscribers(&new_event);
+                self.stash_debounced(&new_event);
+                self.notify_raw(&new_event, Delivered::Passed);
             }
 
             // Restore the callback references if it wasn't replaced during callback
@@ -200,6 +1982,168 @@ impl<T: Clone + PartialEq> OnMutate<T> {
 
         // We're done draining
         self.draining.set(false);
+        self.inspect("drained");
+
+        // Side events (e.g. `IndexChange`) wait for a drain like any other
+        let drained_side = self.flush_side_events();
+
+        if drained_any || drained_side {
+            self.notify_stable();
+        }
+    }
+
+    /// Hand the settled value to every `subscribe_stable()` subscriber that hasn't seen it yet
+    fn notify_stable(&self) {
+        // A guard delivering its `checkpoint()` still holds the value, which isn't settled yet
+        if self.mutating.get() {
+            return;
+        }
+
+        if self.stable.borrow().is_empty() || self.notifying_stable.replace(true) {
+            return;
+        }
+
+        // A stable callback may change the value again; start over with the new value until a full pass leaves it put (or queued)
+        'settle: while self.queue.borrow().is_empty() {
+            let seq = self.seq.get();
+            let value = self.get_val();
+            let ids: Vec<SubscriptionId> = self.stable.borrow().iter().map(|sub| sub.id).collect();
+
+            for id in ids {
+                let callback = self.stable.borrow_mut().iter_mut()
+                    .find(|sub| sub.id == id)
+                    .filter(|sub| sub.last.as_ref().is_none_or(|last| self.differs(last, &value)))
+                    .and_then(|sub| sub.callback.take());
+
+                let Some(mut callback) = callback else { continue };
+                callback(&value);
+
+                // Put it back, unless it unsubscribed while running
+                if let Some(sub) = self.stable.borrow_mut().iter_mut().find(|sub| sub.id == id) {
+                    sub.last = Some(value.clone());
+                    sub.callback = Some(callback);
+                }
+
+                if self.seq.get() != seq {
+                    continue 'settle;
+                }
+            }
+
+            break;
+        }
+
+        self.notifying_stable.set(false);
+    }
+}
+
+/// Debugging helpers for `Debug` values
+impl<T: Clone + PartialEq + fmt::Debug> OnMutate<T> {
+    /// The most recent recorded event as `"old -> new [tag]"` (the tag part only if tagged); needs `with_history()`
+    pub fn last_change_debug(&self) -> Option<String> {
+        let history = self.history.borrow();
+        let last = &history.back()?.event;
+
+        Some(match &last.tag {
+            Some(tag) => format!("{:?} -> {:?} [{tag}]", last.old, last.new),
+            None => format!("{:?} -> {:?}", last.old, last.new),
+        })
+    }
+}
+
+/// Per-mutation controls handed to `with_mut_ctx()` closures
+#[derive(Clone, Debug, Default)]
+pub struct MutationCtx {
+    tag: Option<String>,
+    suppressed: bool,
+    forced: bool,
+}
+
+impl MutationCtx {
+    /// Tag the event (if one is emitted)
+    pub fn tag(&mut self, tag: impl Into<String>) {
+        self.tag = Some(tag.into());
+    }
+
+    /// Don't emit an event, even if the value changed
+    pub fn suppress(&mut self) {
+        self.suppressed = true;
+    }
+
+    /// Emit an event even if the value didn't change; `suppress()` still wins
+    pub fn force(&mut self) {
+        self.forced = true;
+    }
+}
+
+/// Clears the "mid-mutation" flag when dropped, even if the mutation unwinds
+struct MutationScope<'a>(&'a Cell<bool>);
+
+/// One level of `batch()` nesting, released on drop (even when unwinding)
+struct BatchScope<'a>(&'a Cell<u32>);
+
+impl<'a> BatchScope<'a> {
+    fn enter(depth: &'a Cell<u32>) -> Self {
+        depth.set(depth.get() + 1);
+        Self(depth)
+    }
+}
+
+/// Pauses notifications until dropped (even when unwinding), then restores whatever pausing was in effect before
+struct PauseScope<'a> {
+    paused: &'a Cell<bool>,
+    was_paused: bool,
+}
+
+impl<'a> PauseScope<'a> {
+    fn enter(paused: &'a Cell<bool>) -> Self {
+        Self { was_paused: paused.replace(true), paused }
+    }
+}
+
+impl<T: Clone + PartialEq> Drop for OnMutate<T> {
+    fn drop(&mut self) {
+        // Never deliver while unwinding, a panicking callback would abort
+        if self.flush_on_drop && !std::thread::panicking() {
+            self.drain_queue(None);
+        }
+    }
+}
+
+impl Drop for BatchScope<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+impl Drop for PauseScope<'_> {
+    fn drop(&mut self) {
+        self.paused.set(self.was_paused);
+    }
+}
+
+impl Drop for MutationScope<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Returned by `subscribe_scoped()`; unsubscribes when dropped
+#[derive(Debug)]
+pub struct SubscriptionGuard<'a, T: Clone + PartialEq> {
+    owner: &'a OnMutate<T>,
+    id: SubscriptionId,
+}
+
+impl<T: Clone + PartialEq> SubscriptionGuard<'_, T> {
+    /// Id of the guarded subscription
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+}
+
+impl<T: Clone + PartialEq> Drop for SubscriptionGuard<'_, T> {
+    fn drop(&mut self) {
+        self.owner.unsubscribe(self.id);
     }
 }
 
@@ -208,6 +2152,72 @@ pub struct OnMutationChange<'a, T: Clone + PartialEq> {
     old: T,
     borrow: Option<RefMut<'a, T>>,
     tag: Option<String>,
+    scope: Option<MutationScope<'a>>,
+    on_drop: Option<DropHook<'a, T>>,
+    checkpointed: bool, // `checkpoint()` emitted something, so the drop settles the monitor even if nothing changed since
+}
+
+/// Slot filled by an `on_drop_result()` closure once its guard drops with a change
+#[derive(Debug)]
+pub struct DropResult<R>(Rc<RefCell<Option<R>>>);
+
+impl<R> DropResult<R> {
+    /// Has the guard dropped with a change (and the closure run)?
+    pub fn is_ready(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// Take the computed result, if the closure has run
+    pub fn take(&self) -> Option<R> {
+        self.0.borrow_mut().take()
+    }
+}
+
+impl<'a, T: Clone + PartialEq> OnMutationChange<'a, T> {
+    /// Clone of the value as it stands mid-mutation, read through the guard's own borrow
+    pub fn snapshot(&self) -> T {
+        (**self).clone()
+    }
+
+    /// Roll the value back to how it was when the guard was created (or at the last checkpoint); nothing more is emitted on drop
+    pub fn cancel(&mut self) {
+        if let Some(borrow) = self.borrow.as_mut() {
+            **borrow = self.old.clone();
+        }
+    }
+
+    /// Set (or replace) the tag attached to the event emitted on drop
+    pub fn set_tag(&mut self, tag: impl Into<String>) {
+        self.tag = Some(tag.into());
+    }
+
+    /// Emit what changed since the guard was created (or the last checkpoint) without dropping the guard; the drop then only reports later changes
+    ///
+    /// The event is delivered right away (unless the monitor is buffered, batching or already draining). The guard still holds the value, so callbacks only get the event's clones: reading the monitor from them fails, and mutating it panics like any nested mutation. `subscribe_stable()` subscribers wait for the guard to drop
+    ///
+    pub fn checkpoint(&mut self, tag: impl Into<Option<String>>) {
+        let Some(borrow) = self.borrow.as_ref() else { return };
+
+        if self.owner.differs(borrow, &self.old) {
+            let new_clone = (**borrow).clone();
+            let old = std::mem::replace(&mut self.old, new_clone.clone());
+            self.owner.push_event(Mutate::new(old, new_clone, tag.into()));
+            self.checkpointed = true;
+            self.owner.auto_drain();
+        }
+    }
+
+    /// Compute something from the event this guard emits on drop; the result lands in the returned slot (which stays empty if nothing changed)
+    ///
+    /// Calling it again replaces the previous closure
+    ///
+    pub fn on_drop_result<R: 'a>(&mut self, f: impl FnOnce(&Mutate<T>) -> R + 'a) -> DropResult<R> {
+        let slot = Rc::new(RefCell::new(None));
+        let out = slot.clone();
+
+        self.on_drop = Some(Box::new(move |evt| *out.borrow_mut() = Some(f(evt))));
+        DropResult(slot)
+    }
 }
 
 // Dereferences the value
@@ -216,7 +2226,7 @@ impl<'a, T: Clone + PartialEq> std::ops::Deref for OnMutationChange<'a, T> {
 
     fn deref(&self) -> &Self::Target {
         let rm = self.borrow.as_ref().expect("released");
-        &*rm
+        rm
     }
 }
 
@@ -232,15 +2242,86 @@ impl<'a, T: Clone + PartialEq> std::ops::DerefMut for OnMutationChange<'a, T> {
 impl<'a, T: Clone + PartialEq> Drop for OnMutationChange<'a, T> {
     fn drop(&mut self) {
         if let Some(borrow) = self.borrow.take() {
-            let value_mutated = *borrow != self.old;
+            let value_mutated = self.owner.differs(&borrow, &self.old);
+            let unseen_write = !value_mutated && self.owner.loose_detection();
             let new_clone = borrow.clone();
 
             // Release before pushing to queue (this including draining the queue if applicable)
             drop(borrow);
+            self.scope.take();
+            self.owner.run_hook(&self.owner.post_hook);
 
             if value_mutated {
-                self.owner.queue_event(Mutate::new(self.old.clone(), new_clone, self.tag.clone()));
+                let new_event = Mutate::new(self.old.clone(), new_clone, self.tag.clone());
+
+                if let Some(on_drop) = self.on_drop.take() {
+                    on_drop(&new_event);
+                }
+
+                self.owner.queue_event(new_event);
+            } else {
+                if unseen_write {
+                    self.owner.bump_writes();
+                }
+
+                self.owner.inspect("suppressed");
+
+                if self.checkpointed {
+                    self.owner.auto_drain();
+                    self.owner.notify_stable();
+                }
             }
         }
     }
 }
+
+/// Anything with a length, so we can watch collections grow or shrink
+pub trait HasLen {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> HasLen for Vec<T> {
+    fn len(&self) -> usize { Vec::len(self) }
+}
+
+impl<T> HasLen for VecDeque<T> {
+    fn len(&self) -> usize { VecDeque::len(self) }
+}
+
+impl HasLen for String {
+    fn len(&self) -> usize { String::len(self) }
+}
+
+impl<K, V, S> HasLen for HashMap<K, V, S> {
+    fn len(&self) -> usize { HashMap::len(self) }
+}
+
+impl<T, S> HasLen for HashSet<T, S> {
+    fn len(&self) -> usize { HashSet::len(self) }
+}
+
+impl<K, V> HasLen for BTreeMap<K, V> {
+    fn len(&self) -> usize { BTreeMap::len(self) }
+}
+
+impl<T> HasLen for BTreeSet<T> {
+    fn len(&self) -> usize { BTreeSet::len(self) }
+}
+
+/// Length observation for any value implementing `HasLen`
+impl<T: Clone + PartialEq + HasLen> OnMutate<T> {
+    /// Fires with `(old_len, new_len)` only when a mutation changes the length; content-only changes are ignored
+    pub fn on_len_change(&self, mut cb: impl FnMut(usize, usize) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<T>| {
+            let (old_len, new_len) = (evt.old.len(), evt.new.len());
+
+            if old_len != new_len {
+                cb(old_len, new_len);
+            }
+        }));
+    }
+}
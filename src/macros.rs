@@ -0,0 +1,48 @@
+/*
+    Boilerplate generators for observed structs
+*/
+
+/// Generate a trait of observed, per-field setters for `OnMutate<YourStruct>`
+///
+/// Each setter assigns one field through `with_mut()`, tagging the event with the field's name; assigning an equal value emits nothing
+///
+/// ```
+/// use mutation_monitor::{observed_setters, OnMutate};
+///
+/// #[derive(Clone, PartialEq)]
+/// struct Point { x: i32, y: i32 }
+///
+/// observed_setters! {
+///     pub trait PointSetters for Point {
+///         x: i32 => set_x_observed,
+///         y: i32 => set_y_observed,
+///     }
+/// }
+///
+/// let on = OnMutate::new(Point { x: 0, y: 0 }, |evt| assert_eq!(evt.tag.as_deref(), Some("x")));
+/// on.set_x_observed(5);
+/// ```
+///
+#[macro_export]
+macro_rules! observed_setters {
+    (
+        $vis:vis trait $trait_name:ident for $ty:ty {
+            $( $field:ident : $field_ty:ty => $setter:ident ),+ $(,)?
+        }
+    ) => {
+        $vis trait $trait_name {
+            $(
+                #[doc = concat!("Set `", stringify!($field), "`, emitting an event tagged `\"", stringify!($field), "\"` if it changed")]
+                fn $setter(&self, value: $field_ty);
+            )+
+        }
+
+        impl $trait_name for $crate::OnMutate<$ty> {
+            $(
+                fn $setter(&self, value: $field_ty) {
+                    self.with_mut(stringify!($field).to_string(), |v| v.$field = value);
+                }
+            )+
+        }
+    };
+}
@@ -0,0 +1,60 @@
+/*
+    JSON Patch (RFC 6902) deltas between serialized snapshots
+*/
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{Mutate, OnMutate};
+
+impl<T: Clone + PartialEq + Serialize> OnMutate<T> {
+    /// Fires with a JSON Patch (an array of `add`/`remove`/`replace` operations) turning each delivered event's `old` into its `new`
+    ///
+    /// Objects are diffed key by key; anything else (arrays, scalars, a non-object root) that differs is replaced whole. Events that fail to serialize or serialize identically don't fire
+    ///
+    pub fn on_json_patch(&self, mut cb: impl FnMut(Value) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<T>| {
+            let (Ok(old), Ok(new)) = (serde_json::to_value(&evt.old), serde_json::to_value(&evt.new)) else {
+                return;
+            };
+
+            let mut ops = Vec::new();
+            diff(&old, &new, "", &mut ops);
+
+            if !ops.is_empty() {
+                cb(Value::Array(ops));
+            }
+        }));
+    }
+}
+
+/// Append the operations turning `old` into `new` (both found at the JSON Pointer `path`)
+fn diff(old: &Value, new: &Value, path: &str, ops: &mut Vec<Value>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => diff_objects(old, new, path, ops),
+        _ if old != new => ops.push(json!({ "op": "replace", "path": path, "value": new })),
+        _ => {}
+    }
+}
+
+fn diff_objects(old: &Map<String, Value>, new: &Map<String, Value>, path: &str, ops: &mut Vec<Value>) {
+    for (key, old_value) in old {
+        let child = format!("{path}/{}", escape(key));
+
+        match new.get(key) {
+            Some(new_value) => diff(old_value, new_value, &child, ops),
+            None => ops.push(json!({ "op": "remove", "path": child })),
+        }
+    }
+
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            ops.push(json!({ "op": "add", "path": format!("{path}/{}", escape(key)), "value": new_value }));
+        }
+    }
+}
+
+/// Escape a key for use as a JSON Pointer segment (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,81 @@
+/*
+    Shared watchers for a single path into the value
+*/
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::typed::SideListeners;
+use crate::{Mutate, OnMutate};
+
+/// Handle to one path into a monitored value (e.g. `state.user.profile.name`), returned by `watch_path()`
+///
+/// The path is computed once per delivered event, no matter how many subscribers share the handle, and subscribers only hear about events that changed the value at the path. Cloning the handle shares the same subscribers
+///
+#[derive(Clone)]
+pub struct PathWatcher<U: Clone + PartialEq> {
+    last: Rc<RefCell<U>>, // Value at the path as of the last delivered event
+    listeners: Rc<SideListeners<Mutate<U>>>, // Notified with the path's old/new (and the event's tag)
+}
+
+/// A projection handle (e.g. one half of `split()`); the same thing as a `PathWatcher`
+pub type Projected<U> = PathWatcher<U>;
+
+impl<U: Clone + PartialEq + fmt::Debug> fmt::Debug for PathWatcher<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PathWatcher")
+            .field("last", &self.last.borrow())
+            .field("listeners", &"<listeners>")
+            .finish()
+    }
+}
+
+impl<U: Clone + PartialEq> PathWatcher<U> {
+    /// Subscribe to changes of the value at this path
+    pub fn subscribe(&self, cb: impl FnMut(&Mutate<U>) + 'static) {
+        self.listeners.push(Box::new(cb));
+    }
+
+    /// Value at the path as of the last delivered event
+    pub fn get(&self) -> U {
+        self.last.borrow().clone()
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> OnMutate<T> {
+    /// Watch the value at `path`; the returned handle's subscribers fire only when a delivered event changes it
+    pub fn watch_path<U: Clone + PartialEq + 'static>(&self, path: impl Fn(&T) -> U + 'static) -> PathWatcher<U> {
+        let watcher = PathWatcher {
+            last: Rc::new(RefCell::new(path(&self.mut_value.borrow()))),
+            listeners: Rc::new(SideListeners::new()),
+        };
+
+        let (last, listeners) = (watcher.last.clone(), watcher.listeners.clone());
+
+        self.add_listener(Box::new(move |evt: &Mutate<T>| {
+            let new = path(&evt.new);
+
+            if *last.borrow() == new {
+                return;
+            }
+
+            let old = last.replace(new.clone());
+            listeners.notify(&Mutate::new(old, new, evt.tag.clone()));
+        }));
+
+        watcher
+    }
+}
+
+/// Independent observers for each half of a pair
+impl<A, B> OnMutate<(A, B)>
+where
+    A: Clone + PartialEq + 'static,
+    B: Clone + PartialEq + 'static,
+{
+    /// Split into one handle per element; each only fires when its own element changes
+    pub fn split(&self) -> (Projected<A>, Projected<B>) {
+        (self.watch_path(|pair| pair.0.clone()), self.watch_path(|pair| pair.1.clone()))
+    }
+}
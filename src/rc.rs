@@ -0,0 +1,56 @@
+/*
+    Monitoring large values behind `Rc`, without deep clones
+*/
+
+use std::rc::Rc;
+
+use crate::{Mutate, OnMutate};
+
+/// `OnMutate<Rc<T>>` for large, read-heavy values
+///
+/// Snapshots and `get_val()` only bump a reference count, and change detection short-circuits on pointer equality before falling back to `PartialEq`. `replace()`/`replace_rc()` never clone `T`; `with_mut()` is copy-on-write, so it deep-clones `T` on every call (no-ops included), since the event keeps the old `Rc`
+///
+/// Full type implementation: `impl<T: PartialEq> OnMutateRc<T> {}`
+///
+#[derive(Debug)]
+pub struct OnMutateRc<T: PartialEq> {
+    inner: OnMutate<Rc<T>>, // Monitor doing the actual work
+}
+
+impl<T: PartialEq> OnMutateRc<T> {
+    /// New data being ingested; events carry `Rc<T>` old/new
+    pub fn new<F>(value: T, callback: F) -> Self
+    where F: FnMut(&Mutate<Rc<T>>) + 'static
+    {
+        Self {
+            inner: OnMutate::new(Rc::new(value), callback).with_comparator(|a, b| Rc::ptr_eq(a, b) || a == b),
+        }
+    }
+
+    /// Get the current value; a cheap `Rc` clone that stays pointer-equal until the value changes
+    pub fn get_val(&self) -> Rc<T> {
+        self.inner.get_val()
+    }
+
+    /// Replace the entire value; notify if different
+    pub fn replace(&self, new_value: T) {
+        self.inner.replace(Rc::new(new_value));
+    }
+
+    /// Replace with an existing `Rc`; notify if it's neither the same allocation nor equal
+    pub fn replace_rc(&self, new_value: Rc<T>) {
+        self.inner.replace(new_value);
+    }
+
+    /// Mutate copy-on-write; the value is always cloned into a new `Rc` first (even if `f` changes nothing), since the event keeps the old one
+    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R
+    where T: Clone
+    {
+        self.inner.with_mut(tag, |rc| f(Rc::make_mut(rc)))
+    }
+
+    /// The underlying `OnMutate<Rc<T>>`, for everything else (subscriptions, history, ...)
+    pub fn monitor(&self) -> &OnMutate<Rc<T>> {
+        &self.inner
+    }
+}
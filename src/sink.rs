@@ -0,0 +1,93 @@
+/*
+    Pluggable delivery backends
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{Mutate, OnMutate};
+
+/// Where a monitor made with `new_with_sink()` delivers its events
+pub trait EventSink<T> {
+    fn deliver(&mut self, event: &Mutate<T>);
+}
+
+/// Sink wrapping a plain closure
+#[derive(Debug)]
+pub struct ClosureSink<F>(pub F);
+
+impl<T, F: FnMut(&Mutate<T>)> EventSink<T> for ClosureSink<F> {
+    fn deliver(&mut self, event: &Mutate<T>) {
+        (self.0)(event);
+    }
+}
+
+/// Sink collecting every event into a shared `Vec`; clones share the same storage, so keep one to read it back
+#[derive(Debug)]
+pub struct VecSink<T>(Rc<RefCell<Vec<Mutate<T>>>>);
+
+impl<T> VecSink<T> {
+    /// Empty sink
+    pub fn new() -> Self {
+        Self(Rc::new(RefCell::new(Vec::new())))
+    }
+
+    /// Clones of every event collected so far
+    pub fn events(&self) -> Vec<Mutate<T>>
+    where T: Clone
+    {
+        self.0.borrow().clone()
+    }
+
+    /// Take every event collected so far
+    pub fn take(&self) -> Vec<Mutate<T>> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    /// Number of events collected so far
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Has nothing been collected (or has everything been taken)?
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+impl<T> Clone for VecSink<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for VecSink<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> EventSink<T> for VecSink<T> {
+    fn deliver(&mut self, event: &Mutate<T>) {
+        self.0.borrow_mut().push(event.clone());
+    }
+}
+
+/// Sink sending a clone of every event down an `mpsc` channel; events are dropped once the receiver is gone
+#[cfg(feature = "channel")]
+#[derive(Debug)]
+pub struct ChannelSink<T>(pub std::sync::mpsc::Sender<Mutate<T>>);
+
+#[cfg(feature = "channel")]
+impl<T: Clone> EventSink<T> for ChannelSink<T> {
+    fn deliver(&mut self, event: &Mutate<T>) {
+        let _ = self.0.send(event.clone());
+    }
+}
+
+impl<T: Clone + PartialEq> OnMutate<T> {
+    /// New data being ingested, delivered to `sink` instead of a callback
+    pub fn new_with_sink(value: T, mut sink: impl EventSink<T> + 'static) -> Self {
+        Self::new(value, move |evt| sink.deliver(evt))
+    }
+}
@@ -0,0 +1,41 @@
+/*
+    Snapshotting a monitor's value and pending queue via `serde`
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Mutate, OnMutate};
+
+/// Everything needed to restore a monitor: the current value and any undelivered events
+///
+/// Callbacks and subscribers can't be serialized, so they need re-attaching to whichever monitor imports this
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SerializableState<T: Clone + PartialEq> {
+    pub value: T,
+    pub queue: Vec<Mutate<T>>,
+}
+
+impl<T: Clone + PartialEq> OnMutate<T> {
+    /// Capture the current value and pending queue
+    pub fn export_state(&self) -> SerializableState<T> {
+        SerializableState {
+            value: self.get_val(),
+            queue: self.queue.borrow().iter().cloned().collect(),
+        }
+    }
+
+    /// Restore a captured state; the value is replaced silently and the queue is delivered on the next drain
+    pub fn import_state(&self, state: SerializableState<T>) {
+        let scope = self.enter_mutation();
+
+        if let Some(baseline) = &self.external_baseline {
+            *baseline.borrow_mut() = state.value.clone();
+        }
+
+        *self.mut_value.borrow_mut() = state.value;
+        *self.queue.borrow_mut() = state.queue.into();
+        drop(scope);
+        self.bump_seq();
+    }
+}
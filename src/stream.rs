@@ -0,0 +1,128 @@
+/*
+    Async consumption of mutation events via `futures::Stream`
+*/
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use futures::Stream;
+
+use crate::{Mutate, OnMutate};
+
+/// State shared between the monitor's listener and the stream handle
+struct Shared<T: Clone + PartialEq> {
+    buffer: VecDeque<Mutate<T>>,
+    capacity: Option<usize>, // `None` buffers everything
+    waker: Option<Waker>,
+    closed: bool, // The monitor (and with it, the listener) has been dropped
+}
+
+/// The listener side of the stream; dropping it (with the monitor) ends the stream
+struct Feed<T: Clone + PartialEq> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T: Clone + PartialEq> Feed<T> {
+    /// Buffer an event; returns `false` once the stream is gone, so the listener can be removed
+    fn push(&self, new_event: &Mutate<T>) -> bool {
+        // Nobody is listening anymore, don't buffer
+        if Rc::strong_count(&self.shared) == 1 {
+            return false;
+        }
+
+        let mut shared = self.shared.borrow_mut();
+
+        // Backpressure: once the buffer is full we drop the oldest event
+        if let Some(capacity) = shared.capacity {
+            if capacity == 0 {
+                return true;
+            }
+
+            while shared.buffer.len() >= capacity {
+                shared.buffer.pop_front();
+            }
+        }
+
+        shared.buffer.push_back(new_event.clone());
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+}
+
+impl<T: Clone + PartialEq> Drop for Feed<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.closed = true;
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A `Stream` of every mutation delivered by an `OnMutate<T>`
+///
+/// The stream ends once the monitor it was created from is dropped and the buffer is empty
+///
+pub struct MutateStream<T: Clone + PartialEq> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T: Clone + PartialEq> MutateStream<T> {
+    /// Number of events buffered and not yet polled
+    pub fn buffered(&self) -> usize {
+        self.shared.borrow().buffer.len()
+    }
+}
+
+impl<T: Clone + PartialEq> Stream for MutateStream<T> {
+    type Item = Mutate<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.borrow_mut();
+
+        if let Some(new_event) = shared.buffer.pop_front() {
+            return Poll::Ready(Some(new_event));
+        }
+
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> OnMutate<T> {
+    /// Stream every future mutation; events are buffered until polled
+    pub fn stream(&self) -> MutateStream<T> {
+        self.stream_with_capacity(None)
+    }
+
+    /// Stream every future mutation, keeping at most `capacity` events (the oldest are dropped first)
+    pub fn stream_bounded(&self, capacity: usize) -> MutateStream<T> {
+        self.stream_with_capacity(Some(capacity))
+    }
+
+    fn stream_with_capacity(&self, capacity: Option<usize>) -> MutateStream<T> {
+        let shared = Rc::new(RefCell::new(Shared {
+            buffer: VecDeque::new(),
+            capacity,
+            waker: None,
+            closed: false,
+        }));
+
+        let feed = Feed { shared: shared.clone() };
+        self.add_listener_while(move |new_event: &Mutate<T>| feed.push(new_event));
+
+        MutateStream { shared }
+    }
+}
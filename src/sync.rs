@@ -0,0 +1,264 @@
+/*
+    Thread-safe monitoring via `Arc<Mutex<T>>`
+*/
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::Mutate;
+
+/// Boxed callback invoked for every delivered mutation; must be `Send` so it can live on a worker thread
+type SyncCallback<T> = Box<dyn FnMut(&Mutate<T>) + Send + 'static>;
+
+/// Lock, recovering the data if a callback panicked while holding the lock
+fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Queued events plus whether some thread is currently draining them
+struct SyncQueue<T> {
+    events: VecDeque<Mutate<T>>,
+    draining: bool,
+}
+
+/// Background thread delivering events handed off through a channel
+struct Worker<T> {
+    tx: Option<mpsc::Sender<Mutate<T>>>,
+    handle: Mutex<Option<JoinHandle<()>>>, // Taken by whoever joins first: the drop, or a send that found the worker dead
+}
+
+/// Thread-safe counterpart to `OnMutate<T>`
+///
+/// The value lives in a `Mutex`, and events are queued and delivered without holding it, same as the single-threaded monitor. Whichever thread finds the queue idle drains it, unless `with_worker_thread()` moved delivery to a dedicated thread
+///
+/// Full type implementation: `impl<T: Clone + PartialEq + Send + 'static> SyncOnMutate<T> {}`
+///
+pub struct SyncOnMutate<T: Clone + PartialEq + Send + 'static> {
+    mut_value: Arc<Mutex<T>>, // Actual value being ingested (possibly shared, see `from_arc_mutex()`)
+    external_baseline: Option<Mutex<T>>, // Last value this monitor saw, only tracked for shared values
+    callback_ref: Mutex<Option<SyncCallback<T>>>, // Callback for the ingested value (moved to the worker, if any)
+    queue: Mutex<SyncQueue<T>>, // Simple queue for maintaing incoming data
+    worker: Option<Worker<T>>, // Set by `with_worker_thread()`
+}
+
+// `SyncOnMutate` must stay shareable across threads, unlike `OnMutate` (see the `OnMutate` docs)
+const _: fn() = || {
+    fn assert_send_sync<X: Send + Sync>() {}
+    assert_send_sync::<SyncOnMutate<i32>>();
+};
+
+impl<T: Clone + PartialEq + Send + 'static> fmt::Debug for SyncOnMutate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncOnMutate")
+            .field("mut_value", &"<value>")
+            .field("external_baseline", &self.external_baseline.as_ref().map(|_| "<baseline>"))
+            .field("callback_ref", &"<callback>")
+            .field("queue", &"<queue>")
+            .field("worker", &self.worker.is_some())
+            .finish()
+    }
+}
+
+impl<T: Clone + PartialEq + Send + 'static> SyncOnMutate<T> {
+    /// New data being ingested
+    pub fn new<F>(value: T, callback: F) -> Self
+    where F: FnMut(&Mutate<T>) + Send + 'static
+    {
+        Self::new_in(Arc::new(Mutex::new(value)), Box::new(callback))
+    }
+
+    /// Observe a value that's already shared elsewhere as `Arc<Mutex<T>>`
+    ///
+    /// Mutations through this monitor lock the shared mutex. External writes that bypass the monitor don't emit events on their own; call `poll_external()` to pick them up
+    ///
+    pub fn from_arc_mutex<F>(shared: Arc<Mutex<T>>, callback: F) -> Self
+    where F: FnMut(&Mutate<T>) + Send + 'static
+    {
+        let baseline = lock(&shared).clone();
+        let mut monitor = Self::new_in(shared, Box::new(callback));
+        monitor.external_baseline = Some(Mutex::new(baseline));
+        monitor
+    }
+
+    fn new_in(mut_value: Arc<Mutex<T>>, callback: SyncCallback<T>) -> Self {
+        Self {
+            mut_value,
+            external_baseline: None,
+            callback_ref: Mutex::new(Some(callback)),
+            queue: Mutex::new(SyncQueue { events: VecDeque::new(), draining: false }),
+            worker: None,
+        }
+    }
+
+    /// Deliver events on a dedicated background thread, so mutations never wait on callbacks
+    ///
+    /// On drop, the worker finishes delivering whatever was handed to it before the thread is joined
+    ///
+    /// If the callback panics on the worker, delivery stops there: the next mutation re-raises that panic on the mutating thread, as an inline callback's panic would have been raised
+    ///
+    pub fn with_worker_thread(mut self) -> Self {
+        if self.worker.is_some() {
+            return self;
+        }
+
+        let (tx, rx) = mpsc::channel::<Mutate<T>>();
+        let mut callback_opt = lock(&self.callback_ref).take();
+
+        let handle = thread::spawn(move || {
+            for new_event in rx {
+                if let Some(ref mut callback_ref) = callback_opt {
+                    (callback_ref)(&new_event);
+                }
+            }
+        });
+
+        self.worker = Some(Worker { tx: Some(tx), handle: Mutex::new(Some(handle)) });
+        self
+    }
+
+    /// Get the current mutated value
+    pub fn get_val(&self) -> T {
+        lock(&self.mut_value).clone()
+    }
+
+    /// Replace the entire value; notify if different
+    pub fn replace(&self, new_value: T) {
+        let mut current = lock(&self.mut_value);
+
+        if *current != new_value {
+            let new_event = Mutate::new(current.clone(), new_value.clone(), None);
+            *current = new_value;
+            self.update_baseline(&current);
+
+            // Release before pushing to queue (this including draining the queue if applicable)
+            drop(current);
+            self.queue_event(new_event);
+        }
+    }
+
+    /// Mutate; notify once if changed + add a context tag if applicable
+    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        let tag = tag.into();
+
+        let mut guard = lock(&self.mut_value);
+        let old = guard.clone();
+        let out = f(&mut guard);
+        let new_snapshot = (*guard != old).then(|| guard.clone());
+
+        if let Some(new_snapshot) = &new_snapshot {
+            self.update_baseline(new_snapshot);
+        }
+
+        // Release before pushing to queue (this including draining the queue if applicable)
+        drop(guard);
+
+        if let Some(new_snapshot) = new_snapshot {
+            self.queue_event(Mutate::new(old, new_snapshot, tag));
+        }
+
+        out
+    }
+
+    /// Emit an event if a shared value was changed outside this monitor since it last looked; returns whether it did
+    pub fn poll_external(&self) -> bool {
+        let Some(baseline) = &self.external_baseline else { return false };
+
+        let new_event = {
+            let current = lock(&self.mut_value);
+            let mut baseline = lock(baseline);
+
+            if *baseline == *current {
+                return false;
+            }
+
+            Mutate::new(std::mem::replace(&mut *baseline, current.clone()), current.clone(), None)
+        };
+
+        self.queue_event(new_event);
+        true
+    }
+
+    /// Remember `value` as seen; called with the value lock held so external writes can't slip in between
+    fn update_baseline(&self, value: &T) {
+        if let Some(baseline) = &self.external_baseline {
+            *lock(baseline) = value.clone();
+        }
+    }
+
+    /// Hand the event to the worker, or queue it and drain if no other thread is draining
+    fn queue_event(&self, new_event: Mutate<T>) {
+        if let Some(worker) = &self.worker {
+            if let Some(tx) = &worker.tx {
+                // The worker only stops receiving if the callback panicked; don't lose events silently, surface the panic here
+                if tx.send(new_event).is_err() {
+                    let handle = lock(&worker.handle).take();
+
+                    if let Some(Err(payload)) = handle.map(JoinHandle::join) {
+                        panic::resume_unwind(payload);
+                    }
+
+                    panic!("the worker thread's callback panicked, so events can no longer be delivered");
+                }
+
+                return;
+            }
+        }
+
+        {
+            let mut queue = lock(&self.queue);
+            queue.events.push_back(new_event);
+
+            // Another thread (or an outer call on this one) is draining and will pick it up
+            if queue.draining {
+                return;
+            }
+
+            queue.draining = true;
+        }
+
+        loop {
+            // Check and clear `draining` under the same lock producers push under, so no event is stranded
+            let batch = {
+                let mut queue = lock(&self.queue);
+
+                if queue.events.is_empty() {
+                    queue.draining = false;
+                    break;
+                }
+
+                std::mem::take(&mut queue.events)
+            };
+
+            let mut callback_opt = lock(&self.callback_ref).take();
+
+            for new_event in batch {
+                if let Some(ref mut callback_ref) = callback_opt {
+                    (callback_ref)(&new_event);
+                }
+            }
+
+            // Restore the callback references if it wasn't replaced during callback
+            let mut slot = lock(&self.callback_ref);
+
+            if slot.is_none() {
+                *slot = callback_opt;
+            }
+        }
+    }
+}
+
+impl<T: Clone + PartialEq + Send + 'static> Drop for SyncOnMutate<T> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.as_mut() {
+            // Closing the channel lets the worker finish the backlog and exit
+            worker.tx.take();
+
+            if let Some(handle) = lock(&worker.handle).take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+/*
+    Monitoring values whose clones can fail
+*/
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::{EventKind, Mutate};
+
+/// Fallible counterpart to `Clone`, for values wrapping resources (handles, descriptors, ...)
+pub trait TryClone: Sized {
+    type Error;
+
+    fn try_clone(&self) -> Result<Self, Self::Error>;
+}
+
+type TryCallback<T> = Box<dyn FnMut(&Mutate<T>) + 'static>;
+type ErrorCallback<E> = Box<dyn FnMut(E) + 'static>;
+
+/// `OnMutate<T>` for `TryClone` values
+///
+/// Snapshots use `try_clone()`; if one fails, the event is skipped and the error goes to the error callback instead. The value itself is still mutated
+///
+pub struct TryOnMutate<T: TryClone + PartialEq> {
+    mut_value: RefCell<T>, // Actual value being ingested
+    callback_ref: RefCell<Option<TryCallback<T>>>, // Callback for the ingested value
+    error_ref: RefCell<Option<ErrorCallback<T::Error>>>, // Receives failed snapshot clones
+    queue: RefCell<VecDeque<Mutate<T>>>, // Simple queue for maintaing incoming data
+    draining: Cell<bool>, // Is the queue currently draining?
+}
+
+impl<T: TryClone + PartialEq> fmt::Debug for TryOnMutate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TryOnMutate")
+            .field("mut_value", &"<value>")
+            .field("callback_ref", &"<callback>")
+            .field("error_ref", &"<error_callback>")
+            .field("queue", &"<queue>")
+            .field("draining", &"<draining>")
+            .finish()
+    }
+}
+
+impl<T: TryClone + PartialEq> TryOnMutate<T> {
+    /// New data being ingested, with a callback for events and one for clone failures
+    pub fn new<F, E>(value: T, callback: F, on_error: E) -> Self
+    where
+        F: FnMut(&Mutate<T>) + 'static,
+        E: FnMut(T::Error) + 'static,
+    {
+        Self {
+            mut_value: RefCell::new(value),
+            callback_ref: RefCell::new(Some(Box::new(callback))),
+            error_ref: RefCell::new(Some(Box::new(on_error))),
+            queue: RefCell::new(VecDeque::new()),
+            draining: Cell::new(false),
+        }
+    }
+
+    /// Try to get a clone of the current value
+    pub fn try_get_val(&self) -> Result<T, T::Error> {
+        self.mut_value.borrow().try_clone()
+    }
+
+    /// Replace the entire value; notify if different
+    ///
+    /// The old value is moved into the event, so only the new value needs a clone
+    ///
+    pub fn replace(&self, new_value: T) {
+        let mut current = self.mut_value.borrow_mut();
+
+        if *current == new_value {
+            return;
+        }
+
+        let old = std::mem::replace(&mut *current, new_value);
+        let new_snapshot = current.try_clone();
+
+        // Release before pushing to queue (this including draining the queue if applicable)
+        drop(current);
+
+        match new_snapshot {
+            Ok(new) => self.queue_event(Mutate { old, new, tag: None, kind: EventKind::Changed }),
+            Err(err) => self.report(err),
+        }
+    }
+
+    /// Mutate; notify once if changed + add a context tag if applicable
+    pub fn with_mut<R>(&self, tag: impl Into<Option<String>>, f: impl FnOnce(&mut T) -> R) -> R {
+        let tag = tag.into();
+
+        let mut borrow = self.mut_value.borrow_mut();
+        let old = borrow.try_clone();
+        let out = f(&mut borrow);
+
+        // Without an "old" snapshot there's nothing to compare against
+        let new_snapshot = match &old {
+            Ok(old) if *borrow != *old => Some(borrow.try_clone()),
+            _ => None,
+        };
+
+        // Release before pushing to queue (this including draining the queue if applicable)
+        drop(borrow);
+
+        match (old, new_snapshot) {
+            (Err(err), _) | (Ok(_), Some(Err(err))) => self.report(err),
+            (Ok(old), Some(Ok(new))) => self.queue_event(Mutate { old, new, tag, kind: EventKind::Changed }),
+            (Ok(_), None) => {}
+        }
+
+        out
+    }
+
+    /// Hand a failed clone to the error callback
+    fn report(&self, err: T::Error) {
+        let cb = self.error_ref.borrow_mut().take();
+
+        if let Some(mut cb) = cb {
+            cb(err);
+
+            let mut slot = self.error_ref.borrow_mut();
+
+            if slot.is_none() {
+                *slot = Some(cb);
+            }
+        }
+    }
+
+    /// Queue an event and drain if not already draining
+    fn queue_event(&self, new_event: Mutate<T>) {
+        self.queue.borrow_mut().push_back(new_event);
+
+        // Already draining, the running drain will pick it up
+        if self.draining.replace(true) {
+            return;
+        }
+
+        loop {
+            let batch = {
+                let mut q = self.queue.borrow_mut();
+                if q.is_empty() { break; }
+                std::mem::take(&mut *q)
+            };
+
+            let mut callback_opt = self.callback_ref.borrow_mut().take();
+
+            for new_event in batch {
+                if let Some(ref mut callback_ref) = callback_opt {
+                    (callback_ref)(&new_event);
+                }
+            }
+
+            // Restore the callback references if it wasn't replaced during callback
+            let mut slot = self.callback_ref.borrow_mut();
+
+            if slot.is_none() {
+                *slot = callback_opt;
+            }
+        }
+
+        self.draining.set(false);
+    }
+}
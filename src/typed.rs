@@ -0,0 +1,470 @@
+/*
+    Observers for specific shapes of value (`Result`, arrays, ...)
+*/
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::{Mutate, OnMutate, Violation};
+
+/// A single slot of an `OnMutate<[E; N]>` changed via `set_index_observed()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexChange<E> {
+    pub idx: usize,
+    pub old: E,
+    pub new: E,
+}
+
+/// A range of an `OnMutate<Vec<E>>` spliced via `replace_range_observed()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeChange<E> {
+    pub range: Range<usize>, // Range that was replaced, in the old vector
+    pub removed: Vec<E>,
+    pub inserted: Vec<E>,
+}
+
+pub(crate) type SideListener<Ev> = Box<dyn FnMut(&Ev) + 'static>;
+
+/// Listeners for events other than `Mutate<T>` (e.g. `IndexChange`), stored as a monitor extension
+pub(crate) struct SideListeners<Ev> {
+    listeners: RefCell<Vec<SideListener<Ev>>>,
+    pending: RefCell<VecDeque<Ev>>, // Queued until the monitor's next drain
+    flushing: Cell<bool>,
+}
+
+impl<Ev> SideListeners<Ev> {
+    pub(crate) fn new() -> Self {
+        Self { listeners: RefCell::new(Vec::new()), pending: RefCell::new(VecDeque::new()), flushing: Cell::new(false) }
+    }
+
+    pub(crate) fn push(&self, listener: SideListener<Ev>) {
+        self.listeners.borrow_mut().push(listener);
+    }
+
+    fn queue(&self, new_event: Ev) {
+        self.pending.borrow_mut().push_back(new_event);
+    }
+
+    pub(crate) fn notify(&self, new_event: &Ev) {
+        // Take the listeners out so they can register more while running
+        let mut running = std::mem::take(&mut *self.listeners.borrow_mut());
+
+        for listener in running.iter_mut() {
+            listener(new_event);
+        }
+
+        let mut slot = self.listeners.borrow_mut();
+        running.append(&mut slot);
+        *slot = running;
+    }
+}
+
+/// A `SideListeners` as seen by the drain, which doesn't know its event type
+trait PendingSide {
+    /// Deliver every queued event; returns whether there were any
+    fn flush(&self) -> bool;
+}
+
+impl<Ev> PendingSide for SideListeners<Ev> {
+    fn flush(&self) -> bool {
+        // Events queued by the listeners themselves are picked up by this loop
+        if self.flushing.replace(true) {
+            return false;
+        }
+
+        let mut flushed = false;
+
+        loop {
+            let next = self.pending.borrow_mut().pop_front();
+            let Some(new_event) = next else { break };
+
+            self.notify(&new_event);
+            flushed = true;
+        }
+
+        self.flushing.set(false);
+        flushed
+    }
+}
+
+/// Every `SideListeners` extension of a monitor, so the drain can flush them; itself an extension
+#[derive(Default)]
+struct SideQueues(RefCell<Vec<Rc<dyn PendingSide>>>);
+
+/// Plumbing for side events, shared by the typed `*_observed()` setters
+impl<T: Clone + PartialEq + 'static> OnMutate<T> {
+    /// The `SideListeners` extension for `Ev`, registered with the drain when first created
+    fn side_listeners<Ev: 'static>(&self) -> Rc<SideListeners<Ev>> {
+        if let Some(listeners) = self.find_extension::<SideListeners<Ev>>() {
+            return listeners;
+        }
+
+        let listeners = self.extension(SideListeners::<Ev>::new);
+        self.extension(SideQueues::default).0.borrow_mut().push(listeners.clone());
+        listeners
+    }
+
+    /// Queue a side event for a change already made in place, then drain as `queue_event()` would; `undo` reverts the change if an invariant rejects it
+    fn queue_side_event<Ev: 'static>(&self, new_event: Ev, undo: impl FnOnce(&mut T)) {
+        if self.admit_in_place(undo) {
+            self.side_listeners::<Ev>().queue(new_event);
+            self.auto_drain();
+        }
+    }
+
+}
+
+impl<T: Clone + PartialEq> OnMutate<T> {
+    /// Deliver every queued side event; returns whether there were any
+    pub(crate) fn flush_side_events(&self) -> bool {
+        let Some(queues) = self.find_extension::<SideQueues>() else { return false };
+        let queues = queues.0.borrow().clone();
+
+        let mut flushed = false;
+
+        // Not `any()`: every queue gets flushed
+        for queue in queues.iter() {
+            flushed |= queue.flush();
+        }
+
+        flushed
+    }
+}
+
+/// Lowest and highest values seen, stored as a monitor extension by `with_extremes()`
+struct Extremes<T> {
+    min: RefCell<T>,
+    max: RefCell<T>,
+}
+
+impl<T: Clone + PartialOrd> Extremes<T> {
+    fn new(value: &T) -> Self {
+        Self { min: RefCell::new(value.clone()), max: RefCell::new(value.clone()) }
+    }
+
+    fn observe(&self, value: &T) {
+        if *value < *self.min.borrow() {
+            *self.min.borrow_mut() = value.clone();
+        }
+
+        if *value > *self.max.borrow() {
+            *self.max.borrow_mut() = value.clone();
+        }
+    }
+
+    fn reset(&self, value: &T) {
+        *self.min.borrow_mut() = value.clone();
+        *self.max.borrow_mut() = value.clone();
+    }
+}
+
+/// Delivered values counted per bucket, stored as a monitor extension by `with_histogram()`
+struct Histogram<T> {
+    bounds: RefCell<Vec<T>>, // Ascending exclusive upper bounds for every bucket but the last
+    counts: RefCell<Vec<u64>>,
+}
+
+impl<T: PartialOrd> Histogram<T> {
+    fn observe(&self, value: &T) {
+        let idx = self.bounds.borrow().partition_point(|bound| bound <= value);
+        self.counts.borrow_mut()[idx] += 1;
+    }
+
+    /// Switch to new bounds, starting the counts over
+    fn reset(&self, bounds: Vec<T>) {
+        *self.counts.borrow_mut() = vec![0; bounds.len() + 1];
+        *self.bounds.borrow_mut() = bounds;
+    }
+}
+
+/// Delivered `(old, new)` pairs and how often each occurred, stored as a monitor extension by `with_transition_tracking()`
+struct Transitions<T>(RefCell<HashMap<(T, T), u64>>);
+
+/// Float equality where `NaN == NaN` and values within `epsilon` of each other are equal
+///
+/// Use it inside `with_comparator()` for structs holding floats, e.g. `with_comparator(|a, b| float_eq(a.x, b.x, 1e-9))`
+///
+pub fn float_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    (a.is_nan() && b.is_nan()) || a == b || (a - b).abs() <= epsilon
+}
+
+/// NaN-safe change detection for floats
+impl OnMutate<f64> {
+    /// Treat `NaN == NaN`, so repeatedly setting `NaN` doesn't emit an event storm
+    pub fn with_float_eq(self) -> Self {
+        self.with_float_epsilon(0.0)
+    }
+
+    /// Like `with_float_eq()`, also treating values within `epsilon` of each other as unchanged
+    pub fn with_float_epsilon(self, epsilon: f64) -> Self {
+        self.with_comparator(move |a, b| float_eq(*a, *b, epsilon))
+    }
+}
+
+/// Identity-based change detection for shared pointers
+impl<U: PartialEq + ?Sized + 'static> OnMutate<Rc<U>> {
+    /// Detect changes with `Rc::ptr_eq` instead of `PartialEq`: swapping in a distinct but equal allocation counts as a change, and the contents are never compared
+    pub fn with_ptr_eq(self) -> Self {
+        self.with_comparator(Rc::ptr_eq)
+    }
+}
+
+/// Identity-based change detection for shared pointers
+impl<U: PartialEq + ?Sized + 'static> OnMutate<Arc<U>> {
+    /// Detect changes with `Arc::ptr_eq` instead of `PartialEq`, see the `Rc` version
+    pub fn with_ptr_eq(self) -> Self {
+        self.with_comparator(Arc::ptr_eq)
+    }
+}
+
+/// Validation for numeric collections
+impl OnMutate<Vec<f64>> {
+    /// Roll back any change that leaves a NaN or infinite element, handing the rejected vector to `on_violation`
+    pub fn with_finite_validation(self, on_violation: impl Fn(&Vec<f64>) + 'static) -> Self {
+        self.with_invariant(|v| v.iter().all(|x| x.is_finite()), Violation::RollbackWith(Box::new(on_violation)))
+    }
+}
+
+/// Observed `Option::get_or_insert`
+impl<V: Clone + PartialEq> OnMutate<Option<V>> {
+    /// Insert `default` if the value is `None` (emitting an event), then return a clone of the inner value
+    pub fn get_or_insert_observed(&self, default: V) -> V {
+        self.with_mut(None, |v| v.get_or_insert(default).clone())
+    }
+}
+
+/// Transition observers for fallible state
+impl<V: Clone + PartialEq, E: Clone + PartialEq> OnMutate<Result<V, E>> {
+    /// Fires with the new value when the state transitions from `Err` into `Ok`; `Ok(a) -> Ok(b)` doesn't fire
+    pub fn on_ok(&self, mut cb: impl FnMut(&V) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<Result<V, E>>| {
+            if let (Err(_), Ok(new)) = (&evt.old, &evt.new) {
+                cb(new);
+            }
+        }));
+    }
+
+    /// Fires with the new error when the state transitions from `Ok` into `Err`; `Err(a) -> Err(b)` doesn't fire
+    pub fn on_err(&self, mut cb: impl FnMut(&E) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<Result<V, E>>| {
+            if let (Ok(_), Err(new)) = (&evt.old, &evt.new) {
+                cb(new);
+            }
+        }));
+    }
+}
+
+/// Variant (discriminant) transitions, e.g. for enum state machines
+impl<E: Clone + PartialEq> OnMutate<E> {
+    /// Fires with `(old, new)` only when the enum variant changes; data changes within a variant are ignored
+    pub fn on_variant_change(&self, mut cb: impl FnMut(&E, &E) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<E>| {
+            if std::mem::discriminant(&evt.old) != std::mem::discriminant(&evt.new) {
+                cb(&evt.old, &evt.new);
+            }
+        }));
+    }
+}
+
+/// Threshold alerts for ordered values (typically numeric metrics)
+impl<T: Clone + PartialOrd + 'static> OnMutate<T> {
+    /// Fires with `true` when the value rises above `threshold` and `false` when it falls back to `<= threshold`; changes that stay on one side don't fire
+    pub fn on_threshold(&self, threshold: T, mut cb: impl FnMut(bool) + 'static) {
+        let above = Cell::new(*self.mut_value.borrow() > threshold);
+
+        self.add_listener(Box::new(move |evt: &Mutate<T>| {
+            let now_above = evt.new > threshold;
+
+            if above.replace(now_above) != now_above {
+                cb(now_above);
+            }
+        }));
+    }
+
+    /// Track the lowest and highest values seen (starting with the current one), updated as events are delivered; calling it again changes nothing
+    pub fn with_extremes(self) -> Self {
+        if self.find_extension::<Extremes<T>>().is_some() {
+            return self;
+        }
+
+        let extremes = self.extension(|| Extremes::new(&*self.mut_value.borrow()));
+        self.add_listener(Box::new(move |evt: &Mutate<T>| extremes.observe(&evt.new)));
+        self
+    }
+
+    /// Lowest value seen; `None` unless `with_extremes()` is set
+    pub fn min_seen(&self) -> Option<T> {
+        self.find_extension::<Extremes<T>>().map(|ext| ext.min.borrow().clone())
+    }
+
+    /// Highest value seen (the high-water mark); `None` unless `with_extremes()` is set
+    pub fn max_seen(&self) -> Option<T> {
+        self.find_extension::<Extremes<T>>().map(|ext| ext.max.borrow().clone())
+    }
+
+    /// Count delivered `new` values per bucket; `bounds` are ascending bucket edges, so bucket `i` holds `bounds[i - 1] <= v < bounds[i]` and there's one open-ended bucket at each end
+    ///
+    /// Calling it again switches to the new bounds and starts counting over
+    ///
+    pub fn with_histogram(self, bounds: Vec<T>) -> Self {
+        if let Some(histogram) = self.find_extension::<Histogram<T>>() {
+            histogram.reset(bounds);
+            return self;
+        }
+
+        let histogram = self.extension(|| Histogram { counts: RefCell::new(vec![0; bounds.len() + 1]), bounds: RefCell::new(bounds) });
+        self.add_listener(Box::new(move |evt: &Mutate<T>| histogram.observe(&evt.new)));
+        self
+    }
+
+    /// Counts for each of the `bounds.len() + 1` buckets, lowest first; empty unless `with_histogram()` is set
+    pub fn histogram(&self) -> Vec<u64> {
+        self.find_extension::<Histogram<T>>().map(|ext| ext.counts.borrow().clone()).unwrap_or_default()
+    }
+
+    /// Start tracking extremes over from the current value
+    pub fn reset_extremes(&self) {
+        if let Some(ext) = self.find_extension::<Extremes<T>>() {
+            ext.reset(&self.mut_value.borrow());
+        }
+    }
+}
+
+/// Transition counts for small-domain values (typically state machine states)
+impl<T: Clone + Eq + Hash + 'static> OnMutate<T> {
+    /// Count how often each `old -> new` transition is delivered; calling it again changes nothing
+    pub fn with_transition_tracking(self) -> Self {
+        if self.find_extension::<Transitions<T>>().is_some() {
+            return self;
+        }
+
+        let transitions = self.extension(|| Transitions(RefCell::new(HashMap::new())));
+
+        self.add_listener(Box::new(move |evt: &Mutate<T>| {
+            *transitions.0.borrow_mut().entry((evt.old.clone(), evt.new.clone())).or_insert(0) += 1;
+        }));
+        self
+    }
+
+    /// Count of each `(old, new)` transition delivered so far; empty unless `with_transition_tracking()` is set
+    pub fn transitions(&self) -> HashMap<(T, T), u64> {
+        self.find_extension::<Transitions<T>>().map(|ext| ext.0.borrow().clone()).unwrap_or_default()
+    }
+}
+
+/// Allocation observation for vectors
+impl<E: Clone + PartialEq + 'static> OnMutate<Vec<E>> {
+    /// Fires with `(old_capacity, new_capacity)` when a delivered change comes with a reallocation
+    ///
+    /// Clones don't keep capacity, so this reads the live vector at delivery time rather than the event snapshots
+    ///
+    pub fn on_capacity_change(&self, mut cb: impl FnMut(usize, usize) + 'static) {
+        let value = self.mut_value.clone();
+        let last = Cell::new(value.borrow().capacity());
+
+        self.add_listener(Box::new(move |_: &Mutate<Vec<E>>| {
+            // A guard delivering a `checkpoint()` still holds the vector; the next delivery catches up
+            let Ok(current) = value.try_borrow() else { return };
+            let capacity = current.capacity();
+            let old = last.replace(capacity);
+
+            if old != capacity {
+                cb(old, capacity);
+            }
+        }));
+    }
+
+    /// Fires with `(idx, old, new)` for each element a delivered change modified
+    ///
+    /// Only same-length changes are diffed; pushes, removals and other length changes don't fire (see `on_len_change()` for those)
+    ///
+    pub fn on_element_changed(&self, mut cb: impl FnMut(usize, &E, &E) + 'static) {
+        self.add_listener(Box::new(move |evt: &Mutate<Vec<E>>| {
+            if evt.old.len() != evt.new.len() {
+                return;
+            }
+
+            for (idx, (old, new)) in evt.old.iter().zip(&evt.new).enumerate() {
+                if old != new {
+                    cb(idx, old, new);
+                }
+            }
+        }));
+    }
+
+    /// Listen for `RangeChange` events emitted by `replace_range_observed()`
+    pub fn on_range_change(&self, cb: impl FnMut(&RangeChange<E>) + 'static) {
+        self.side_listeners::<RangeChange<E>>().push(Box::new(cb));
+    }
+
+    /// Splice `replacement` into `range`, emitting a `RangeChange` only if the removed and inserted items differ
+    ///
+    /// Panics if the range is out of bounds or decreasing, like `Vec::splice`. Range events only reach `on_range_change()` listeners; the main callback and subscribers aren't notified, since we never build a whole-vector `Mutate`
+    ///
+    /// Otherwise the splice goes through the usual pipeline, like `set_index_observed()`'s changes
+    ///
+    pub fn replace_range_observed(&self, range: Range<usize>, replacement: Vec<E>) {
+        // Before touching the value, so a nested call fails with the nested-mutation message
+        let scope = self.enter_mutation();
+
+        let len = self.mut_value.borrow().len();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "range {range:?} out of bounds for vector of length {len}",
+        );
+
+        self.run_hook(&self.pre_hook);
+
+        let mut borrow = self.mut_value.borrow_mut();
+        let removed: Vec<E> = borrow.splice(range.clone(), replacement.iter().cloned()).collect();
+        let change = (removed != replacement).then_some(RangeChange { range, removed, inserted: replacement });
+
+        // Release before notifying
+        drop(borrow);
+        drop(scope);
+        self.run_hook(&self.post_hook);
+
+        if let Some(change) = change {
+            let at = change.range.start..change.range.start + change.inserted.len();
+            let removed = change.removed.clone();
+            self.queue_side_event(change, |value| { value.splice(at, removed); });
+        }
+    }
+}
+
+/// Per-slot observation for fixed-size arrays, without cloning the whole array
+impl<E: Clone + PartialEq + 'static, const N: usize> OnMutate<[E; N]> {
+    /// Listen for `IndexChange` events emitted by `set_index_observed()`
+    pub fn on_index_change(&self, cb: impl FnMut(&IndexChange<E>) + 'static) {
+        self.side_listeners::<IndexChange<E>>().push(Box::new(cb));
+    }
+
+    /// Set one slot, emitting an `IndexChange` only if it differs
+    ///
+    /// Panics if `idx` is out of bounds, like normal indexing. Index events only reach `on_index_change()` listeners; the main callback and subscribers aren't notified, since we never build a whole-array `Mutate`
+    ///
+    /// Otherwise the change goes through the usual pipeline: invariants are checked, a buffered or batching monitor holds the event until it drains, and a paused one discards it. History and `with_monotonic()` only track whole-value events
+    ///
+    pub fn set_index_observed(&self, idx: usize, value: E) {
+        let scope = self.enter_mutation();
+        self.run_hook(&self.pre_hook);
+
+        let mut borrow = self.mut_value.borrow_mut();
+        let old = std::mem::replace(&mut borrow[idx], value);
+        let change = (old != borrow[idx]).then(|| IndexChange { idx, old, new: borrow[idx].clone() });
+
+        // Release before notifying
+        drop(borrow);
+        drop(scope);
+        self.run_hook(&self.post_hook);
+
+        if let Some(change) = change {
+            let old = change.old.clone();
+            self.queue_side_event(change, |value| value[idx] = old);
+        }
+    }
+}
@@ -1,8 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
-    use mutation_monitor::{Mutate, OnMutate};
+    use mutation_monitor::{net_change, Mutate, OnMutate, OverflowPolicy, UNTAGGED};
 
     #[test]
     fn notifies_on_change() {
@@ -62,4 +62,2592 @@ mod tests {
         on.with_mut(None, |v| *v += 1);
         assert_eq!(on.get_val(), 3);
     }
+
+    #[test]
+    fn len_change_ignores_content_changes() {
+        let seen: Rc<RefCell<Vec<(usize, usize)>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(vec![1, 2, 3], |_| {});
+        on.on_len_change(move |old, new| s2.borrow_mut().push((old, new)));
+
+        on.with_mut(None, |v| v[0] = 10);
+        assert!(seen.borrow().is_empty());
+
+        on.with_mut(None, |v| v.push(4));
+        assert_eq!(*seen.borrow(), vec![(3, 4)]);
+    }
+
+    #[test]
+    fn try_with_guard_errors_when_borrowed() {
+        let on = OnMutate::new(0, |_| {});
+
+        on.with_mut(None, |v| {
+            assert!(on.try_with_guard().is_err());
+            *v += 1;
+        });
+
+        {
+            let mut g = on.try_with_guard().expect("value is not borrowed");
+            *g += 1;
+        }
+
+        assert_eq!(on.get_val(), 2);
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_receives_events() {
+        use futures::executor::block_on;
+        use futures::StreamExt;
+
+        let on = OnMutate::new(0, |_| {});
+        let mut stream = on.stream();
+
+        on.replace(1);
+        on.with_mut(Some("two".into()), |v| *v = 2);
+
+        let first = block_on(stream.next()).unwrap();
+        let second = block_on(stream.next()).unwrap();
+        assert_eq!((first.old, first.new), (0, 1));
+        assert_eq!(second.tag.as_deref(), Some("two"));
+
+        // Dropping the monitor ends the stream
+        drop(on);
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn bounded_stream_drops_oldest() {
+        use futures::executor::block_on;
+        use futures::StreamExt;
+
+        let on = OnMutate::new(0, |_| {});
+        let stream = on.stream_bounded(2);
+
+        for i in 1..=4 {
+            on.replace(i);
+        }
+
+        drop(on);
+        let seen: Vec<i32> = block_on(stream.map(|evt| evt.new).collect());
+        assert_eq!(seen, vec![3, 4]);
+    }
+
+    #[test]
+    fn net_change_collapses_history() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        assert!(net_change::<i32>(&[]).is_none());
+
+        on.with_mut(Some("step".into()), |v| *v = 1);
+        let single = net_change(&seen.borrow()).unwrap();
+        assert_eq!((single.old, single.new), (0, 1));
+        assert_eq!(single.tag.as_deref(), Some("step"));
+        assert!(!single.is_noop());
+
+        on.replace(5);
+        on.replace(7);
+        let multi = net_change(&seen.borrow()).unwrap();
+        assert_eq!((multi.old, multi.new), (0, 7));
+        assert!(multi.tag.is_none());
+
+        // Ending where we started nets to nothing
+        on.replace(0);
+        assert!(net_change(&seen.borrow()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "nested mutation on the same OnMutate is not allowed")]
+    fn nested_with_mut_panics_clearly() {
+        let on = OnMutate::new(0, |_| {});
+        on.with_mut(None, |_| on.with_mut(None, |v| *v += 1));
+    }
+
+    #[test]
+    fn nested_guard_is_released_after_drop() {
+        let on = OnMutate::new(0, |_| {});
+
+        {
+            let mut g = on.with_guard();
+            *g += 1;
+            assert!(on.try_with_guard().is_err());
+        }
+
+        on.with_mut(None, |v| *v += 1);
+        assert_eq!(on.get_val(), 2);
+    }
+
+    #[test]
+    fn prelude_brings_extension_traits() {
+        use mutation_monitor::prelude::*;
+        use std::collections::HashMap;
+
+        let count = Rc::new(RefCell::new(0));
+        let c2 = count.clone();
+        let list = OnMutate::new(vec![1], move |_| *c2.borrow_mut() += 1);
+
+        list.push_observed(2);
+        assert_eq!(list.pop_observed(), Some(2));
+        list.clear_observed();
+        list.clear_observed();
+        assert_eq!(*count.borrow(), 3);
+
+        let map: OnMutate<HashMap<&str, i32>> = OnMutate::new(HashMap::new(), |_| {});
+        assert_eq!(map.insert_observed("a", 1), None);
+        assert_eq!(map.remove_observed(&"a"), Some(1));
+        assert!(map.get_val().is_empty());
+    }
+
+    #[test]
+    fn tag_normalizer_cleans_tags() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()))
+            .with_tag_normalizer(|tag| tag.trim().to_lowercase());
+
+        on.with_mut(Some(" Save ".into()), |v| *v = 1);
+        on.replace(2);
+
+        let seen = seen.borrow();
+        assert_eq!(seen[0].tag.as_deref(), Some("save"));
+        assert!(seen[1].tag.is_none());
+    }
+
+    #[test]
+    fn replace_many_counts_changes() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        let changes = on.replace_many([1, 1, 2, 2, 2, 0]);
+        assert_eq!(changes, 3);
+        assert_eq!(seen.borrow().len(), 3);
+        assert_eq!(on.get_val(), 0);
+    }
+
+    #[test]
+    fn result_transitions_fire_on_variant_change() {
+        let oks: Rc<RefCell<Vec<u32>>> = Rc::new(RefCell::new(vec![]));
+        let errs: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let (o2, e2) = (oks.clone(), errs.clone());
+
+        let on: OnMutate<Result<u32, String>> = OnMutate::new(Ok(1), |_| {});
+        on.on_ok(move |v| o2.borrow_mut().push(*v));
+        on.on_err(move |e| e2.borrow_mut().push(e.clone()));
+
+        on.replace(Ok(2));
+        on.replace(Err("down".into()));
+        on.replace(Err("still down".into()));
+        on.replace(Ok(3));
+
+        assert_eq!(*oks.borrow(), vec![3]);
+        assert_eq!(*errs.borrow(), vec!["down".to_string()]);
+    }
+
+    #[test]
+    fn guard_scope_can_cancel() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        for attempt in [5, -10] {
+            on.with_guard_scope(None, |g| {
+                **g += attempt;
+
+                if **g < 0 {
+                    g.cancel();
+                } else {
+                    g.set_tag("accepted");
+                }
+            });
+        }
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].tag.as_deref(), Some("accepted"));
+        assert_eq!(on.get_val(), 5);
+    }
+
+    fn capped(policy: OverflowPolicy) -> Vec<i32> {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.new))
+            .buffered()
+            .with_max_queue(3, policy);
+
+        for i in 1..=5 {
+            on.replace(i);
+        }
+
+        assert_eq!(on.queue_len(), 3);
+        assert!(seen.borrow().is_empty());
+
+        on.drain_now();
+        assert_eq!(on.queue_len(), 0);
+        seen.take()
+    }
+
+    #[test]
+    fn max_queue_drop_oldest() {
+        assert_eq!(capped(OverflowPolicy::DropOldest), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn max_queue_drop_newest() {
+        assert_eq!(capped(OverflowPolicy::DropNewest), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn max_queue_block_never_drains_a_buffered_monitor() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.new))
+            .buffered()
+            .with_max_queue(3, OverflowPolicy::Block);
+
+        for i in 1..=5 {
+            on.replace(i);
+        }
+
+        // Over the cap rather than delivering behind `drain_now()`'s back
+        assert_eq!(on.queue_len(), 5);
+        assert!(seen.borrow().is_empty());
+
+        on.drain_now();
+        assert_eq!(*seen.borrow(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn max_queue_block_drains_what_the_budget_left() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.new))
+            .with_event_budget(0)
+            .with_max_queue(2, OverflowPolicy::Block);
+
+        for i in 1..=3 {
+            on.replace(i);
+        }
+
+        // The third event found the queue full and delivered the first two to make room
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+        assert_eq!(on.queue_len(), 1);
+    }
+
+    #[test]
+    fn filtered_subscribers_are_independent() {
+        let evens: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let big: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let (e2, b2) = (evens.clone(), big.clone());
+
+        let on = OnMutate::new(0, |_| {});
+        on.subscribe_filtered(|evt| evt.new % 2 == 0, move |evt| e2.borrow_mut().push(evt.new));
+        let id = on.subscribe_filtered(|evt| evt.new > 2, move |evt| b2.borrow_mut().push(evt.new));
+
+        on.replace_many([1, 2, 3, 4]);
+        assert!(on.unsubscribe(id));
+        assert!(!on.unsubscribe(id));
+        on.replace(6);
+
+        assert_eq!(*evens.borrow(), vec![2, 4, 6]);
+        assert_eq!(*big.borrow(), vec![3, 4]);
+    }
+
+    #[test]
+    fn shared_value_picks_up_external_writes_on_poll() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let shared = Rc::new(RefCell::new(1));
+        let on = OnMutate::from_shared(shared.clone(), move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.replace(2);
+        assert_eq!(*shared.borrow(), 2);
+        assert!(!on.poll_external());
+
+        *shared.borrow_mut() = 10;
+        assert_eq!(on.get_val(), 10);
+        assert_eq!(seen.borrow().len(), 1);
+
+        assert!(on.poll_external());
+        assert!(!on.poll_external());
+
+        let seen = seen.borrow();
+        assert_eq!((seen[1].old, seen[1].new), (2, 10));
+    }
+
+    #[test]
+    fn tag_counts_track_mixed_tags() {
+        let on = OnMutate::new(0, |_| {});
+
+        on.with_mut(Some("inc".into()), |v| *v += 1);
+        on.with_mut(Some("inc".into()), |v| *v += 1);
+        on.with_mut(Some("reset".into()), |v| *v = 0);
+        on.replace(9);
+        on.with_mut(Some("inc".into()), |_| {});
+
+        let counts = on.tag_counts();
+        assert_eq!(counts.get("inc"), Some(&2));
+        assert_eq!(counts.get("reset"), Some(&1));
+        assert_eq!(counts.get(UNTAGGED), Some(&1));
+
+        on.reset_tag_stats();
+        assert!(on.tag_counts().is_empty());
+    }
+
+    #[test]
+    fn subscribe_on_value_fires_on_target_only() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum Status { Idle, Loading, Ready }
+
+        let hits = Rc::new(RefCell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(Status::Idle, |_| {});
+        on.subscribe_on_value(Status::Ready, move |evt| {
+            assert_eq!(evt.old, Status::Loading);
+            *h2.borrow_mut() += 1;
+        });
+
+        on.replace(Status::Loading);
+        assert_eq!(*hits.borrow(), 0);
+        on.replace(Status::Ready);
+        on.replace(Status::Ready);
+        assert_eq!(*hits.borrow(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn state_round_trips_through_serde() {
+        use mutation_monitor::SerializableState;
+
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone())).buffered();
+
+        on.replace(1);
+        on.with_mut(Some("two".into()), |v| *v = 2);
+        let json = serde_json::to_string(&on.export_state()).unwrap();
+
+        on.replace(50);
+        on.drain_now();
+        seen.borrow_mut().clear();
+
+        let state: SerializableState<i32> = serde_json::from_str(&json).unwrap();
+        on.import_state(state);
+        assert_eq!(on.get_val(), 2);
+        assert_eq!(on.queue_len(), 2);
+        assert!(seen.borrow().is_empty());
+
+        on.drain_now();
+        let seen = seen.borrow();
+        assert_eq!((seen[0].old, seen[0].new), (0, 1));
+        assert_eq!(seen[1].tag.as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn subscribe_values_receives_new_values() {
+        let values: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let v2 = values.clone();
+        let on = OnMutate::new(String::new(), |_| {});
+        on.subscribe_values(move |v| v2.borrow_mut().push(v.clone()));
+
+        on.with_mut(None, |s| s.push('a'));
+        on.with_mut(None, |s| s.push('b'));
+        on.replace("ab".into());
+        on.replace("z".into());
+
+        assert_eq!(*values.borrow(), vec!["a", "ab", "z"]);
+    }
+
+    #[test]
+    fn auto_tag_classifies_direction() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(5, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        let classify = |old: &i32, new: &i32| Some(if new > old { "incremented" } else { "decremented" }.to_string());
+        on.with_mut_auto_tag(classify, |v| *v += 2);
+        on.with_mut_auto_tag(classify, |v| *v -= 4);
+        on.with_mut_auto_tag(classify, |_| {});
+
+        let tags: Vec<_> = seen.borrow().iter().map(|evt| evt.tag.clone().unwrap()).collect();
+        assert_eq!(tags, vec!["incremented", "decremented"]);
+    }
+
+    #[test]
+    fn hooks_run_for_every_attempt() {
+        let log: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let (pre, post, cb) = (log.clone(), log.clone(), log.clone());
+
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| cb.borrow_mut().push(format!("event {}", evt.new)));
+        on.set_pre_hook(move |v| pre.borrow_mut().push(format!("pre {v}")));
+        on.set_post_hook(move |v| post.borrow_mut().push(format!("post {v}")));
+
+        on.with_mut(None, |v| *v += 1);
+        on.replace(1);
+        {
+            let mut g = on.with_guard();
+            *g = 2;
+        }
+
+        assert_eq!(*log.borrow(), vec![
+            "pre 0", "post 1", "event 1",
+            "pre 1", "post 1",
+            "pre 1", "post 2", "event 2",
+        ]);
+    }
+
+    #[test]
+    fn string_ext_emits_once_per_change() {
+        use mutation_monitor::StringOnMutateExt;
+
+        let seen: Rc<RefCell<Vec<Mutate<String>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(String::from("hello"), move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.push_str_observed(" world");
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!(seen.borrow()[0].new, "hello world");
+
+        on.truncate_observed(5);
+        on.truncate_observed(50);
+        assert_eq!(seen.borrow().len(), 2);
+        assert_eq!(on.get_val(), "hello");
+
+        on.clear_observed();
+        on.clear_observed();
+        assert_eq!(seen.borrow().len(), 3);
+        assert_eq!(on.get_val(), "");
+    }
+
+    #[test]
+    fn event_budget_limits_each_drain() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.new)).with_event_budget(3);
+
+        assert_eq!(on.replace_many(1..=10), 10);
+        assert_eq!(*seen.borrow(), vec![1, 2, 3]);
+        assert_eq!(on.queue_len(), 7);
+
+        on.drain_now();
+        assert_eq!(*seen.borrow(), (1..=10).collect::<Vec<_>>());
+        assert_eq!(on.queue_len(), 0);
+    }
+
+    #[test]
+    fn tag_helpers_cover_tagged_and_untagged() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.with_mut(Some("save".into()), |v| *v = 1);
+        on.replace(2);
+
+        let seen = seen.borrow();
+        let (tagged, untagged) = (&seen[0], &seen[1]);
+
+        assert!(tagged.has_tag("save"));
+        assert!(!tagged.has_tag("load"));
+        assert!(tagged.is_tagged());
+        assert_eq!(tagged.tag_or("none"), "save");
+
+        assert!(!untagged.has_tag("save"));
+        assert!(!untagged.is_tagged());
+        assert_eq!(untagged.tag_or("none"), "none");
+    }
+
+    #[test]
+    fn array_index_changes_are_targeted() {
+        use mutation_monitor::IndexChange;
+
+        let whole = Rc::new(RefCell::new(0));
+        let changes: Rc<RefCell<Vec<IndexChange<u8>>>> = Rc::new(RefCell::new(vec![]));
+        let (w2, c2) = (whole.clone(), changes.clone());
+
+        let on = OnMutate::new([0u8; 64], move |_| *w2.borrow_mut() += 1);
+        on.on_index_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.set_index_observed(10, 7);
+        on.set_index_observed(10, 7);
+
+        assert_eq!(*changes.borrow(), vec![IndexChange { idx: 10, old: 0, new: 7 }]);
+        assert_eq!(*whole.borrow(), 0);
+        assert_eq!(on.get_val()[10], 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn array_index_out_of_bounds_panics() {
+        let on = OnMutate::new([0u8; 4], |_| {});
+        on.set_index_observed(4, 1);
+    }
+
+    #[test]
+    fn computed_only_recomputes_after_changes() {
+        let runs = Rc::new(Cell::new(0));
+        let r2 = runs.clone();
+        let on = OnMutate::new(vec![1, 2, 3], |_| {});
+        let sum = on.computed(move |v: &Vec<i32>| {
+            r2.set(r2.get() + 1);
+            v.iter().sum::<i32>()
+        });
+
+        assert_eq!(sum.get(), 6);
+        assert_eq!(sum.get(), 6);
+        assert_eq!(runs.get(), 1);
+
+        on.with_mut(None, |v| v[0] = 1);
+        assert_eq!(sum.get(), 6);
+        assert_eq!(runs.get(), 1);
+
+        on.with_mut(None, |v| v.push(4));
+        assert_eq!(sum.get(), 10);
+        assert_eq!(sum.get(), 10);
+        assert_eq!(runs.get(), 2);
+        assert_eq!(on.current_seq(), 1);
+    }
+
+    #[test]
+    fn try_on_mutate_skips_events_when_clone_fails() {
+        use mutation_monitor::{TryClone, TryOnMutate};
+
+        // Cloning "succeeds" until the shared budget runs out
+        #[derive(Debug, PartialEq)]
+        struct Handle { id: u32, budget: Rc<Cell<u32>> }
+
+        impl TryClone for Handle {
+            type Error = String;
+
+            fn try_clone(&self) -> Result<Self, String> {
+                match self.budget.get() {
+                    0 => Err(format!("cannot clone handle {}", self.id)),
+                    n => {
+                        self.budget.set(n - 1);
+                        Ok(Handle { id: self.id, budget: self.budget.clone() })
+                    }
+                }
+            }
+        }
+
+        let events = Rc::new(RefCell::new(0));
+        let errors: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let (ev2, er2) = (events.clone(), errors.clone());
+        let budget = Rc::new(Cell::new(1));
+
+        let on = TryOnMutate::new(
+            Handle { id: 1, budget: budget.clone() },
+            move |_| *ev2.borrow_mut() += 1,
+            move |err| er2.borrow_mut().push(err),
+        );
+
+        // The first clone ("old") works, the second ("new") fails
+        on.with_mut(None, |h| h.id = 2);
+        assert_eq!(*events.borrow(), 0);
+        assert_eq!(*errors.borrow(), vec!["cannot clone handle 2".to_string()]);
+
+        // Replace only needs to clone the new value
+        budget.set(1);
+        on.replace(Handle { id: 3, budget: budget.clone() });
+        assert_eq!(*events.borrow(), 1);
+        assert!(on.try_get_val().is_err());
+    }
+
+    #[test]
+    fn reset_to_default_fires_once() {
+        let seen: Rc<RefCell<Vec<Mutate<String>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(String::from("draft"), move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.reset_to_default();
+        on.reset_to_default();
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].old, "draft");
+        assert!(seen[0].has_tag("reset"));
+        assert_eq!(on.get_val(), "");
+    }
+
+    #[test]
+    fn subscribers_run_by_priority() {
+        let order: Rc<RefCell<Vec<&str>>> = Rc::new(RefCell::new(vec![]));
+        let on = OnMutate::new(0, |_| {});
+
+        for (priority, name) in [(0, "renderer"), (10, "validator"), (0, "logger"), (-5, "metrics"), (10, "guard")] {
+            let o2 = order.clone();
+            on.subscribe_with_priority(priority, move |_| o2.borrow_mut().push(name));
+        }
+
+        on.replace(1);
+        assert_eq!(*order.borrow(), vec!["validator", "guard", "renderer", "logger", "metrics"]);
+    }
+
+    #[test]
+    fn changed_since_flips_after_mutation() {
+        let on = OnMutate::new(0, |_| {});
+        let frame = on.current_seq();
+
+        on.replace(0);
+        assert!(!on.changed_since(frame));
+
+        on.replace(1);
+        assert!(on.changed_since(frame));
+        assert!(!on.changed_since(on.current_seq()));
+    }
+
+    #[test]
+    fn mutation_ctx_suppresses_and_forces() {
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.with_mut_ctx(|v, ctx| {
+            *v = 1;
+            ctx.suppress();
+        });
+        assert!(seen.borrow().is_empty());
+        assert_eq!(on.get_val(), 1);
+
+        on.with_mut_ctx(|_, ctx| {
+            ctx.tag("refresh");
+            ctx.force();
+        });
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!((seen[0].old, seen[0].new), (1, 1));
+        assert!(seen[0].has_tag("refresh"));
+    }
+
+    #[test]
+    fn float_eq_treats_nan_as_equal() {
+        let count = Rc::new(RefCell::new(0));
+        let c2 = count.clone();
+        let on = OnMutate::new(1.0_f64, move |_| *c2.borrow_mut() += 1).with_float_eq();
+
+        on.replace(f64::NAN);
+        on.replace(f64::NAN);
+        assert_eq!(*count.borrow(), 1);
+
+        let c3 = count.clone();
+        let near = OnMutate::new(1.0_f64, move |_| *c3.borrow_mut() += 1).with_float_epsilon(1e-6);
+        near.replace(1.0 + 1e-9);
+        near.replace(2.0);
+        assert_eq!(*count.borrow(), 2);
+    }
+
+    #[test]
+    fn comparator_works_on_structs() {
+        use mutation_monitor::float_eq;
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Point { x: f64, y: f64 }
+
+        let count = Rc::new(RefCell::new(0));
+        let c2 = count.clone();
+        let on = OnMutate::new(Point { x: f64::NAN, y: 0.0 }, move |_| *c2.borrow_mut() += 1)
+            .with_comparator(|a, b| float_eq(a.x, b.x, 0.0) && float_eq(a.y, b.y, 0.0));
+
+        on.with_mut(None, |p| p.x = f64::NAN);
+        assert_eq!(*count.borrow(), 0);
+        on.with_mut(None, |p| p.y = 1.0);
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_only_emits_on_insert() {
+        let count = Rc::new(RefCell::new(0));
+        let c2 = count.clone();
+        let on: OnMutate<Option<String>> = OnMutate::new(None, move |_| *c2.borrow_mut() += 1);
+
+        assert_eq!(on.get_or_insert_observed("first".into()), "first");
+        assert_eq!(*count.borrow(), 1);
+
+        assert_eq!(on.get_or_insert_observed("second".into()), "first");
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    #[test]
+    fn worker_thread_delivers_off_thread() {
+        use mutation_monitor::SyncOnMutate;
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        let seen: Arc<Mutex<Vec<(i32, thread::ThreadId)>>> = Arc::new(Mutex::new(vec![]));
+        let s2 = seen.clone();
+        let on = SyncOnMutate::new(0, move |evt: &Mutate<i32>| {
+            s2.lock().unwrap().push((evt.new, thread::current().id()));
+        }).with_worker_thread();
+
+        for i in 1..=50 {
+            on.replace(i);
+        }
+        on.with_mut(None, |v| *v += 1);
+
+        // Dropping joins the worker after it has processed everything
+        drop(on);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 51);
+        assert_eq!(seen.last().unwrap().0, 51);
+        assert!(seen.iter().all(|(_, id)| *id != thread::current().id()));
+    }
+
+    #[test]
+    fn sync_monitor_drains_inline_without_worker() {
+        use mutation_monitor::SyncOnMutate;
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        let seen = Arc::new(Mutex::new(0));
+        let s2 = seen.clone();
+        let on = Arc::new(SyncOnMutate::new(0, move |_| *s2.lock().unwrap() += 1));
+
+        let handles: Vec<_> = (0..4).map(|_| {
+            let on = on.clone();
+            thread::spawn(move || for _ in 0..25 { on.with_mut(None, |v| *v += 1) })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(on.get_val(), 100);
+        assert_eq!(*seen.lock().unwrap(), 100);
+    }
+
+    #[test]
+    fn inspect_reports_lifecycle_labels() {
+        let labels: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let l2 = labels.clone();
+        let on = OnMutate::new(1, |_| {}).with_inspect(move |label| l2.borrow_mut().push(label.to_string()));
+
+        on.replace(2);
+        on.replace(2); // no-op
+        on.with_mut(None, |v| *v += 1);
+
+        assert_eq!(*labels.borrow(), vec![
+            "attempt", "changed", "drained",
+            "attempt", "suppressed",
+            "attempt", "changed", "drained",
+        ]);
+    }
+
+    #[test]
+    fn on_variant_change_ignores_intra_variant_updates() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum State { Idle, Loading(u8), Done(String) }
+
+        let seen: Rc<RefCell<Vec<(State, State)>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(State::Idle, |_| {});
+        on.on_variant_change(move |old, new| s2.borrow_mut().push((old.clone(), new.clone())));
+
+        on.replace(State::Loading(0));
+        on.replace(State::Loading(50)); // same variant
+        on.replace(State::Loading(100)); // same variant
+        on.replace(State::Done("ok".into()));
+        on.replace(State::Done("still ok".into())); // same variant
+
+        assert_eq!(*seen.borrow(), vec![
+            (State::Idle, State::Loading(0)),
+            (State::Loading(100), State::Done("ok".into())),
+        ]);
+    }
+
+    #[test]
+    fn shrink_queue_reclaims_capacity_after_burst() {
+        let on = OnMutate::new(0, |_| {}).buffered();
+        on.replace_many(1..=1000);
+        assert!(on.queue_capacity() >= 1000);
+
+        // Not empty yet, so nothing is reclaimed
+        on.shrink_queue();
+        assert!(on.queue_capacity() >= 1000);
+
+        on.drain_now();
+        on.shrink_queue();
+        assert!(on.queue_capacity() < 1000);
+    }
+
+    #[test]
+    fn fork_is_independent_of_the_original() {
+        let original_fired = Rc::new(Cell::new(0));
+        let of = original_fired.clone();
+        let on = OnMutate::new(vec![1, 2], move |_| of.set(of.get() + 1));
+        on.subscribe(|_| panic!("subscribers aren't carried over to forks"));
+
+        let seen: Rc<RefCell<Vec<Mutate<Vec<i32>>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let fork = on.fork(move |evt| s2.borrow_mut().push(evt.clone()));
+
+        fork.with_mut(None, |v| v.push(3));
+
+        assert_eq!(fork.get_val(), vec![1, 2, 3]);
+        assert_eq!(on.get_val(), vec![1, 2]);
+        assert_eq!(original_fired.get(), 0);
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!(seen.borrow()[0].old, vec![1, 2]);
+    }
+
+    #[test]
+    fn replace_debounced_defers_until_poll_after_delay() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.clone()))
+            .with_clock(move || n2.get())
+            .with_debounce(Duration::from_millis(100));
+
+        assert!(!on.replace_debounced(1));
+        assert!(!on.replace_debounced(2)); // overwrites the pending 1
+        assert!(on.has_pending());
+        assert_eq!(on.get_val(), 0);
+
+        now.set(start + Duration::from_millis(50));
+        assert!(!on.poll());
+        assert!(seen.borrow().is_empty());
+
+        now.set(start + Duration::from_millis(100));
+        assert!(on.poll());
+        assert!(!on.has_pending());
+        assert_eq!(on.get_val(), 2);
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!((seen.borrow()[0].old, seen.borrow()[0].new), (0, 2));
+    }
+
+    #[test]
+    fn on_capacity_change_fires_on_reallocation_only() {
+        let seen: Rc<RefCell<Vec<(usize, usize)>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(Vec::with_capacity(2), |_| {});
+        on.on_capacity_change(move |old, new| s2.borrow_mut().push((old, new)));
+
+        on.with_mut(None, |v| v.push(1));
+        on.with_mut(None, |v| v.push(2));
+        assert!(seen.borrow().is_empty());
+
+        on.with_mut(None, |v| v.push(3)); // beyond capacity
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, 2);
+        assert!(seen[0].1 >= 3);
+    }
+
+    #[test]
+    fn with_version_skips_deep_eq_on_matching_versions() {
+        thread_local! {
+            static DEEP_EQ_CALLS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Clone, Debug)]
+        struct Doc { version: u64, body: Vec<u8> }
+
+        impl PartialEq for Doc {
+            fn eq(&self, other: &Self) -> bool {
+                DEEP_EQ_CALLS.with(|c| c.set(c.get() + 1));
+                self.body == other.body
+            }
+        }
+
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(Doc { version: 1, body: vec![0; 1024] }, move |_| f2.set(f2.get() + 1))
+            .with_version(|d| d.version);
+
+        // Same version: treated as unchanged without comparing bodies
+        on.with_mut(None, |d| d.body[0] = 1);
+        assert_eq!(DEEP_EQ_CALLS.with(Cell::get), 0);
+        assert_eq!(fired.get(), 0);
+
+        // Different version: falls back to `PartialEq`
+        on.with_mut(None, |d| { d.version = 2; d.body[1] = 1; });
+        assert_eq!(DEEP_EQ_CALLS.with(Cell::get), 1);
+        assert_eq!(fired.get(), 1);
+
+        let trusted = OnMutate::new(Doc { version: 1, body: vec![] }, |_| {})
+            .with_version(|d| d.version)
+            .trust_version(true);
+        trusted.with_mut(None, |d| d.version = 2);
+        assert_eq!(DEEP_EQ_CALLS.with(Cell::get), 1);
+    }
+
+    #[test]
+    fn history_keeps_the_last_cap_events() {
+        let on = OnMutate::new(0, |_| {}).with_history(2);
+        on.replace_many([1, 2, 3]);
+
+        let history = on.history();
+        assert_eq!(history.iter().map(|evt| evt.new).collect::<Vec<_>>(), vec![2, 3]);
+
+        on.clear_history();
+        assert!(on.history().is_empty());
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn events_between_queries_a_half_open_range() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_history(10).with_clock(move || n2.get());
+
+        for i in 1..=5 {
+            now.set(start + Duration::from_secs(i));
+            on.replace(i as i32);
+        }
+
+        let at = |secs| start + Duration::from_secs(secs);
+        let news = |events: Vec<Mutate<i32>>| events.iter().map(|evt| evt.new).collect::<Vec<_>>();
+
+        assert_eq!(news(on.events_between(at(2), at(4))), vec![2, 3]);
+        assert_eq!(news(on.events_between(at(0), at(100))), vec![1, 2, 3, 4, 5]);
+        assert!(on.events_between(at(3), at(3)).is_empty());
+    }
+
+    #[test]
+    fn insert_sorted_observed_keeps_order_and_reports_index() {
+        use mutation_monitor::{RangeChange, VecOnMutateExt};
+
+        let changes: Rc<RefCell<Vec<RangeChange<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let c2 = changes.clone();
+        let on = OnMutate::new(vec![], |_| {});
+        on.on_range_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        let indices: Vec<usize> = [5, 1, 3, 9, 3].into_iter().map(|x| on.insert_sorted_observed(x)).collect();
+
+        assert_eq!(indices, vec![0, 0, 1, 3, 2]);
+        assert_eq!(on.get_val(), vec![1, 3, 3, 5, 9]);
+
+        let changes = changes.borrow();
+        assert_eq!(changes.iter().map(|change| change.range.clone()).collect::<Vec<_>>(), indices.iter().map(|&i| i..i).collect::<Vec<_>>());
+        assert_eq!(changes[3], RangeChange { range: 3..3, removed: vec![], inserted: vec![9] });
+    }
+
+    #[test]
+    fn on_mutate_rc_shares_the_value_until_it_changes() {
+        use mutation_monitor::OnMutateRc;
+
+        type Blob = Rc<Vec<u8>>;
+
+        let seen: Rc<RefCell<Vec<Mutate<Blob>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutateRc::new(vec![0u8; 4096], move |evt| s2.borrow_mut().push(evt.clone()));
+
+        let first = on.get_val();
+        assert!(Rc::ptr_eq(&first, &on.get_val()));
+
+        // Equal contents: nothing fires and the original allocation is kept
+        on.replace(vec![0u8; 4096]);
+        assert!(Rc::ptr_eq(&first, &on.get_val()));
+        assert!(seen.borrow().is_empty());
+
+        on.with_mut(None, |v| v[0] = 1);
+        let second = on.get_val();
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert_eq!(first[0], 0);
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert!(Rc::ptr_eq(&seen[0].old, &first));
+        assert!(Rc::ptr_eq(&seen[0].new, &second));
+    }
+
+    #[test]
+    fn on_threshold_fires_once_per_crossing() {
+        let crossings: Rc<RefCell<Vec<bool>>> = Rc::new(RefCell::new(vec![]));
+        let c2 = crossings.clone();
+        let on = OnMutate::new(10.0, |_| {});
+        on.on_threshold(50.0, move |above| c2.borrow_mut().push(above));
+
+        on.replace_many([20.0, 50.0]); // stays at or below
+        on.replace(60.0); // up
+        on.replace(90.0); // stays above
+        on.replace(50.0); // down (back to `<=`)
+        on.replace(40.0); // stays below
+        on.replace(51.0); // up
+
+        assert_eq!(*crossings.borrow(), vec![true, false, true]);
+    }
+
+    #[test]
+    fn replace_checked_rejects_without_touching_the_monitor() {
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(5u32, move |_| f2.set(f2.get() + 1)).with_history(4);
+        let even = |v: &u32| v.is_multiple_of(2);
+
+        let err = on.replace_checked(7, even).unwrap_err();
+        assert_eq!(err.rejected, 7);
+        assert_eq!(err.to_string(), "value rejected by invariant");
+        assert_eq!(on.get_val(), 5);
+        assert_eq!(fired.get(), 0);
+        assert!(on.history().is_empty());
+
+        assert_eq!(on.replace_checked(8, even), Ok(()));
+        assert_eq!(on.get_val(), 8);
+        assert_eq!(fired.get(), 1);
+    }
+
+    #[test]
+    fn sync_from_arc_mutex_detects_external_writes_on_poll() {
+        use mutation_monitor::SyncOnMutate;
+        use std::sync::{Arc, Mutex};
+
+        let shared = Arc::new(Mutex::new(1));
+        let seen: Arc<Mutex<Vec<Mutate<i32>>>> = Arc::new(Mutex::new(vec![]));
+        let s2 = seen.clone();
+        let on = SyncOnMutate::from_arc_mutex(shared.clone(), move |evt| s2.lock().unwrap().push(evt.clone()));
+
+        on.replace(2);
+        assert!(!on.poll_external()); // our own write isn't "external"
+
+        *shared.lock().unwrap() = 10;
+        assert_eq!(on.get_val(), 10);
+        assert!(on.poll_external());
+        assert!(!on.poll_external());
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.iter().map(|evt| (evt.old, evt.new)).collect::<Vec<_>>(), vec![(1, 2), (2, 10)]);
+    }
+
+    #[test]
+    fn tag_map_rewrites_tags_at_delivery() {
+        let seen: Rc<RefCell<Vec<Option<String>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let subscriber_seen: Rc<RefCell<Vec<Option<String>>>> = Rc::new(RefCell::new(vec![]));
+        let ss2 = subscriber_seen.clone();
+
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.tag.clone()))
+            .with_history(8)
+            .with_tag_map(|tag| tag.map(|t| if t.starts_with("secret:") { "<redacted>".into() } else { t }));
+        on.subscribe(move |evt| ss2.borrow_mut().push(evt.tag.clone()));
+
+        on.with_mut("secret:token-123".to_string(), |v| *v += 1);
+        on.with_mut("public".to_string(), |v| *v += 1);
+        on.replace(10);
+
+        let expected = vec![Some("<redacted>".to_string()), Some("public".to_string()), None];
+        assert_eq!(*seen.borrow(), expected);
+        assert_eq!(*subscriber_seen.borrow(), expected);
+        assert_eq!(on.history()[0].tag.as_deref(), Some("secret:token-123"));
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn change_rate_counts_events_in_the_trailing_window() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_history(100).with_clock(move || n2.get());
+
+        // One event every 100ms for 2s
+        for i in 1..=20 {
+            now.set(start + Duration::from_millis(100 * i));
+            on.replace(i as i32);
+        }
+
+        let rate = on.change_rate(Duration::from_secs(1));
+        assert!((rate - 10.0).abs() < 1e-9, "rate was {rate}");
+        assert!((on.change_rate(Duration::from_millis(500)) - 10.0).abs() < 1e-9);
+
+        // Quiet for a while
+        now.set(start + Duration::from_secs(10));
+        assert_eq!(on.change_rate(Duration::from_secs(1)), 0.0);
+        assert_eq!(on.change_rate(Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn replace_and_get_returns_the_stored_value() {
+        // Comparator treats values within 10 as equal, so small changes are rejected
+        let on = OnMutate::new(100, |_| {}).with_comparator(|a: &i32, b: &i32| (a - b).abs() < 10);
+
+        assert_eq!(on.replace_and_get(105), 100);
+        assert_eq!(on.get_val(), 100);
+        assert_eq!(on.replace_and_get(150), 150);
+    }
+
+    #[test]
+    fn subscribe_boxed_fires_like_subscribe() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, |_| {});
+
+        type Plugin = Box<dyn FnMut(&Mutate<i32>)>;
+
+        let plugins: Vec<Plugin> = vec![
+            Box::new(move |evt| s2.borrow_mut().push(evt.new)),
+        ];
+
+        let ids: Vec<_> = plugins.into_iter().map(|cb| on.subscribe_boxed(cb)).collect();
+        on.replace(3);
+        assert_eq!(*seen.borrow(), vec![3]);
+
+        assert!(on.unsubscribe(ids[0]));
+        on.replace(4);
+        assert_eq!(*seen.borrow(), vec![3]);
+    }
+
+    #[test]
+    fn drain_with_routes_queue_through_a_local_consumer() {
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(0, move |_| f2.set(f2.get() + 1)).buffered();
+        on.subscribe(|_| panic!("drain_with bypasses subscribers"));
+
+        on.replace_many([1, 2, 3]);
+        assert_eq!(on.queue_len(), 3);
+
+        let mut flushed = vec![];
+        on.drain_with(|evt| flushed.push((evt.old, evt.new)));
+
+        assert_eq!(flushed, vec![(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(on.queue_len(), 0);
+        assert_eq!(fired.get(), 0);
+    }
+
+    #[test]
+    fn with_mut_notify_on_panic_delivers_partial_changes() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let seen: Rc<RefCell<Vec<Mutate<Vec<i32>>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(vec![], move |evt| s2.borrow_mut().push(evt.clone()));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            on.with_mut_notify_on_panic("partial".to_string(), |v| {
+                v.push(1);
+                panic!("halfway");
+            })
+        }));
+
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "halfway");
+        assert_eq!(on.get_val(), vec![1]);
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!(seen.borrow()[0].new, vec![1]);
+        assert_eq!(seen.borrow()[0].tag.as_deref(), Some("partial"));
+
+        // The monitor is still usable afterwards
+        assert_eq!(on.with_mut_notify_on_panic(None, |v| { v.push(2); v.len() }), 2);
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+    #[test]
+    fn get_val_or_does_not_force_lazy_init() {
+        let inits = Rc::new(Cell::new(0));
+        let i2 = inits.clone();
+        let seen: Rc<RefCell<Vec<Mutate<Vec<u8>>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+
+        let on = OnMutate::new_lazy(move || { i2.set(i2.get() + 1); vec![1, 2, 3] }, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        assert_eq!(on.get_val_or(vec![]), Vec::<u8>::new());
+        assert!(!on.is_initialized());
+        assert_eq!(inits.get(), 0);
+
+        assert_eq!(on.get_val(), vec![1, 2, 3]);
+        assert_eq!(on.get_val_or(vec![]), vec![1, 2, 3]);
+        on.with_mut(None, |v| v.push(4));
+
+        assert_eq!(inits.get(), 1);
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!(seen.borrow()[0].old, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn watch_path_shares_one_path_between_subscribers() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Profile { name: String, age: u32 }
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct State { profile: Profile, clicks: u32 }
+
+        let on = OnMutate::new(State { profile: Profile { name: "ann".into(), age: 30 }, clicks: 0 }, |_| {});
+
+        let computed = Rc::new(Cell::new(0));
+        let c2 = computed.clone();
+        let name = on.watch_path(move |s: &State| { c2.set(c2.get() + 1); s.profile.name.clone() });
+
+        let header: Rc<RefCell<Vec<(String, String)>>> = Rc::new(RefCell::new(vec![]));
+        let sidebar = Rc::new(Cell::new(0));
+        let (h2, sb2) = (header.clone(), sidebar.clone());
+        name.subscribe(move |evt| h2.borrow_mut().push((evt.old.clone(), evt.new.clone())));
+        name.subscribe(move |_| sb2.set(sb2.get() + 1));
+
+        on.with_mut(None, |s| s.clicks += 1);
+        on.with_mut(None, |s| s.profile.age += 1);
+        assert!(header.borrow().is_empty());
+        assert_eq!(sidebar.get(), 0);
+
+        on.with_mut(None, |s| s.profile.name = "bea".into());
+        assert_eq!(*header.borrow(), vec![("ann".to_string(), "bea".to_string())]);
+        assert_eq!(sidebar.get(), 1);
+        assert_eq!(name.get(), "bea");
+
+        // Once at creation, then once per delivered event, however many subscribers there are
+        assert_eq!(computed.get(), 4);
+    }
+
+    #[test]
+    fn replace_if_newer_ignores_stale_updates() {
+        type Update = (u64, &'static str);
+
+        let seen: Rc<RefCell<Vec<Mutate<Update>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new((1, "first"), move |evt| s2.borrow_mut().push(evt.clone()));
+        let version = |v: &Update| v.0;
+
+        assert!(on.replace_if_newer((3, "third"), version));
+        assert!(!on.replace_if_newer((2, "second"), version)); // arrived late
+        assert!(!on.replace_if_newer((3, "third again"), version)); // same version
+
+        assert_eq!(on.get_val(), (3, "third"));
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!(seen.borrow()[0].new, (3, "third"));
+    }
+
+    #[test]
+    fn subscribe_raw_sees_filtered_events_too() {
+        use mutation_monitor::Delivered;
+
+        let delivered = Rc::new(RefCell::new(vec![]));
+        let d2 = delivered.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| d2.borrow_mut().push(evt.new))
+            .with_filter(|evt| evt.new % 2 == 0);
+
+        let subscribed = Rc::new(RefCell::new(vec![]));
+        let sub2 = subscribed.clone();
+        on.subscribe(move |evt| sub2.borrow_mut().push(evt.new));
+
+        let raw = Rc::new(RefCell::new(vec![]));
+        let r2 = raw.clone();
+        on.subscribe_raw(move |evt, delivered| r2.borrow_mut().push((evt.new, delivered)));
+
+        on.replace_many([1, 2, 3, 4]);
+
+        assert_eq!(*delivered.borrow(), vec![2, 4]);
+        assert_eq!(*subscribed.borrow(), vec![2, 4]);
+        assert_eq!(*raw.borrow(), vec![
+            (1, Delivered::Filtered),
+            (2, Delivered::Passed),
+            (3, Delivered::Filtered),
+            (4, Delivered::Passed),
+        ]);
+    }
+
+    #[test]
+    fn diff_since_reports_the_net_change() {
+        let on = OnMutate::new(vec![1], |_| {});
+        let cp = on.checkpoint();
+        assert!(on.diff_since(cp).is_none());
+
+        on.with_mut(None, |v| v.push(2));
+        on.with_mut(None, |v| v.push(3));
+
+        let diff = on.diff_since(cp).unwrap();
+        assert_eq!((diff.old, diff.new), (vec![1], vec![1, 2, 3]));
+
+        // Changing back makes the diff disappear
+        on.replace(vec![1]);
+        assert!(on.diff_since(cp).is_none());
+
+        assert!(on.release_checkpoint(cp));
+        on.replace(vec![]);
+        assert!(on.diff_since(cp).is_none());
+    }
+
+    #[test]
+    fn update_applies_a_functional_change() {
+        let seen: Rc<RefCell<Vec<Mutate<u32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let counter = OnMutate::new(0u32, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        counter.update(|n| n + 1);
+        counter.update(|n| n); // unchanged, no event
+
+        assert_eq!(counter.get_val(), 1);
+        assert_eq!(seen.borrow().len(), 1);
+        assert_eq!((seen.borrow()[0].old, seen.borrow()[0].new), (0, 1));
+
+        counter.set(5);
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+    #[test]
+    fn take_events_capped_consumes_in_chunks() {
+        let on = OnMutate::new(0, |_| panic!("taken events aren't delivered")).buffered();
+        on.replace_many(1..=5);
+
+        let first: Vec<i32> = on.take_events_capped(2).iter().map(|evt| evt.new).collect();
+        assert_eq!(first, vec![1, 2]);
+        assert_eq!(on.queue_len(), 3);
+
+        let rest: Vec<i32> = on.take_events_capped(10).iter().map(|evt| evt.new).collect();
+        assert_eq!(rest, vec![3, 4, 5]);
+        assert!(on.take_events_capped(1).is_empty());
+    }
+
+    #[test]
+    fn new_with_ctx_accumulates_into_owned_context() {
+        #[derive(Default)]
+        struct Stats { events: usize, total_delta: i32 }
+
+        let on = OnMutate::new_with_ctx(0, Stats::default(), |stats, evt: &Mutate<i32>| {
+            stats.events += 1;
+            stats.total_delta += evt.new - evt.old;
+        });
+
+        on.replace(5);
+        on.replace(5);
+        on.with_mut(None, |v| *v -= 2);
+
+        assert_eq!(on.with_ctx(|stats: &Stats| (stats.events, stats.total_delta)), (2, 3));
+    }
+
+    #[test]
+    fn replace_range_observed_emits_precise_range_change() {
+        use mutation_monitor::RangeChange;
+
+        let changes: Rc<RefCell<Vec<RangeChange<char>>>> = Rc::new(RefCell::new(vec![]));
+        let c2 = changes.clone();
+        let on = OnMutate::new("hello world".chars().collect::<Vec<_>>(), |_| panic!("no whole-vector event"));
+        on.on_range_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.replace_range_observed(6..11, "rust!".chars().collect());
+        on.replace_range_observed(0..5, "hello".chars().collect()); // same contents, nothing fires
+
+        assert_eq!(on.get_val().into_iter().collect::<String>(), "hello rust!");
+        assert_eq!(*changes.borrow(), vec![RangeChange {
+            range: 6..11,
+            removed: "world".chars().collect(),
+            inserted: "rust!".chars().collect(),
+        }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn replace_range_observed_panics_on_bad_range() {
+        let on = OnMutate::new(vec![1, 2, 3], |_| {});
+        on.replace_range_observed(2..5, vec![]);
+    }
+
+    #[test]
+    fn subscribe_first_fires_once_then_removes_itself() {
+        let first: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let f2 = first.clone();
+        let on = OnMutate::new(0, |_| {});
+
+        on.replace(1); // before registration
+        let id = on.subscribe_first(move |evt| f2.borrow_mut().push(evt.new));
+        on.replace(1); // no-op, no event
+        on.replace(2);
+        on.replace(3);
+
+        assert_eq!(*first.borrow(), vec![2]);
+        assert!(!on.unsubscribe(id));
+    }
+
+    #[test]
+    fn guard_on_drop_result_captures_the_delta() {
+        let on = OnMutate::new(10i64, |_| {});
+
+        let delta = {
+            let mut guard = on.with_guard();
+            let delta = guard.on_drop_result(|evt| evt.new - evt.old);
+            *guard += 32;
+            assert!(!delta.is_ready());
+            delta
+        };
+
+        assert_eq!(delta.take(), Some(32));
+        assert_eq!(delta.take(), None);
+
+        // No change, no result
+        let untouched = {
+            let mut guard = on.with_guard();
+            guard.on_drop_result(|evt| evt.new - evt.old)
+        };
+        assert!(!untouched.is_ready());
+    }
+
+    #[test]
+    fn split_observes_tuple_halves_independently() {
+        let on = OnMutate::new((1u8, String::from("a")), |_| {});
+        let (left, right) = on.split();
+
+        let left_fired = Rc::new(Cell::new(0));
+        let right_seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let (l2, r2) = (left_fired.clone(), right_seen.clone());
+        left.subscribe(move |_| l2.set(l2.get() + 1));
+        right.subscribe(move |evt| r2.borrow_mut().push(evt.new.clone()));
+
+        on.with_mut(None, |pair| pair.1.push('b'));
+
+        assert_eq!(left_fired.get(), 0);
+        assert_eq!(*right_seen.borrow(), vec!["ab".to_string()]);
+
+        on.with_mut(None, |pair| pair.0 = 2);
+        assert_eq!(left_fired.get(), 1);
+        assert_eq!(right_seen.borrow().len(), 1);
+    }
+
+    #[test]
+    fn replace_forced_emits_even_when_equal() {
+        use mutation_monitor::EventKind;
+
+        let seen: Rc<RefCell<Vec<Mutate<i32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(7, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.replace(7);
+        on.replace_forced(7);
+        on.replace(8);
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 2);
+        assert_eq!((seen[0].old, seen[0].new, seen[0].kind), (7, 7, EventKind::Forced));
+        assert!(seen[0].is_forced());
+        assert_eq!(seen[1].kind, EventKind::Changed);
+    }
+
+    #[test]
+    fn on_element_changed_reports_each_changed_index() {
+        let changed: Rc<RefCell<Vec<(usize, i32, i32)>>> = Rc::new(RefCell::new(vec![]));
+        let c2 = changed.clone();
+        let on = OnMutate::new(vec![1, 2, 3, 4, 5], |_| {});
+        on.on_element_changed(move |idx, old, new| c2.borrow_mut().push((idx, *old, *new)));
+
+        on.with_mut(None, |v| { v[1] = 20; v[4] = 50; });
+        assert_eq!(*changed.borrow(), vec![(1, 2, 20), (4, 5, 50)]);
+
+        on.with_mut(None, |v| v.push(6)); // length change, ignored
+        assert_eq!(changed.borrow().len(), 2);
+    }
+
+    #[test]
+    fn pause_during_discards_events_from_inside() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt: &Mutate<i32>| s2.borrow_mut().push(evt.new));
+
+        let seq = on.current_seq();
+        let out = on.pause_during(|| {
+            on.replace(1);
+            on.with_mut(None, |v| *v += 1);
+            "done"
+        });
+
+        assert_eq!(out, "done");
+        assert_eq!(on.get_val(), 2);
+        assert!(seen.borrow().is_empty());
+        assert_eq!(on.queue_len(), 0);
+        assert!(on.changed_since(seq));
+
+        on.replace(3);
+        assert_eq!(*seen.borrow(), vec![3]);
+    }
+
+    #[test]
+    fn pump_from_applies_available_channel_values() {
+        use std::sync::mpsc;
+
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(0, move |_| f2.set(f2.get() + 1));
+        let (tx, rx) = mpsc::channel();
+
+        for v in [1, 1, 2] {
+            tx.send(v).unwrap();
+        }
+
+        assert_eq!(on.pump_from(&rx), 2);
+        assert_eq!(on.get_val(), 2);
+        assert_eq!(fired.get(), 2);
+
+        // Nothing waiting: returns right away
+        assert_eq!(on.pump_from(&rx), 0);
+    }
+
+    #[test]
+    fn last_change_debug_formats_the_latest_event() {
+        let on = OnMutate::new(vec![1], |_| {}).with_history(4);
+        assert_eq!(on.last_change_debug(), None);
+
+        on.replace(vec![1, 2]);
+        assert_eq!(on.last_change_debug().as_deref(), Some("[1] -> [1, 2]"));
+
+        on.with_mut("grow".to_string(), |v| v.push(3));
+        assert_eq!(on.last_change_debug().as_deref(), Some("[1, 2] -> [1, 2, 3] [grow]"));
+
+        let no_history = OnMutate::new(0, |_| {});
+        no_history.replace(1);
+        assert_eq!(no_history.last_change_debug(), None);
+    }
+
+    #[test]
+    fn guard_checkpoint_emits_incremental_events() {
+        let seen: Rc<RefCell<Vec<Mutate<Vec<i32>>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(vec![], move |evt| s2.borrow_mut().push(evt.clone()));
+
+        {
+            let mut guard = on.with_tag("edit");
+            guard.push(1);
+            guard.checkpoint("step".to_string());
+            guard.checkpoint(None); // nothing new since the last one
+            guard.push(2);
+            guard.checkpoint("step".to_string());
+            assert_eq!(seen.borrow().len(), 2); // delivered at each checkpoint, guard still alive
+            guard.push(3);
+        }
+
+        let seen = seen.borrow();
+        let pairs: Vec<_> = seen.iter().map(|evt| (evt.old.clone(), evt.new.clone(), evt.tag.clone())).collect();
+        assert_eq!(pairs, vec![
+            (vec![], vec![1], Some("step".to_string())),
+            (vec![1], vec![1, 2], Some("step".to_string())),
+            (vec![1, 2], vec![1, 2, 3], Some("edit".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn subscribe_debounced_collapses_bursts_for_one_subscriber() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_clock(move || n2.get());
+
+        let immediate = Rc::new(RefCell::new(vec![]));
+        let debounced: Rc<RefCell<Vec<(i32, i32)>>> = Rc::new(RefCell::new(vec![]));
+        let (i2, d2) = (immediate.clone(), debounced.clone());
+        on.subscribe(move |evt| i2.borrow_mut().push(evt.new));
+        on.subscribe_debounced(Duration::from_millis(100), move |evt| d2.borrow_mut().push((evt.old, evt.new)));
+
+        for (ms, v) in [(0, 1), (30, 2), (60, 3)] {
+            now.set(start + Duration::from_millis(ms));
+            on.replace(v);
+        }
+
+        now.set(start + Duration::from_millis(120));
+        assert!(!on.poll()); // last event was at 60ms
+        assert_eq!(*immediate.borrow(), vec![1, 2, 3]);
+        assert!(debounced.borrow().is_empty());
+
+        now.set(start + Duration::from_millis(160));
+        assert!(on.poll());
+        assert_eq!(*debounced.borrow(), vec![(0, 3)]);
+
+        assert!(!on.poll());
+        assert_eq!(debounced.borrow().len(), 1);
+    }
+
+    #[test]
+    fn try_into_inner_refuses_while_events_are_queued() {
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(vec![1], move |_| f2.set(f2.get() + 1)).buffered();
+        on.with_mut(None, |v| v.push(2));
+
+        let (on, pending) = on.try_into_inner().unwrap_err();
+        assert_eq!(pending, 1);
+
+        on.drain_now();
+        assert_eq!(fired.get(), 1);
+        assert_eq!(on.try_into_inner().ok(), Some(vec![1, 2]));
+
+        let plain = OnMutate::new(3, |_| {});
+        assert_eq!(plain.into_inner(), 3);
+    }
+
+    #[test]
+    fn nested_transactions_drain_once_at_the_outermost() {
+        let drains = Rc::new(Cell::new(0));
+        let d2 = drains.clone();
+        let seen: Rc<RefCell<Vec<Mutate<Vec<&str>>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+
+        let on = OnMutate::new(vec![], move |evt| s2.borrow_mut().push(evt.clone()))
+            .with_inspect(move |label| if label == "drained" { d2.set(d2.get() + 1) });
+
+        on.batch(|| {
+            on.transaction("open".to_string(), |v| v.push("a"));
+
+            on.batch(|| {
+                on.transaction("inner".to_string(), |v| { v.push("b"); v.push("c"); });
+                assert!(seen.borrow().is_empty());
+            });
+
+            on.transaction("close".to_string(), |v| v.push("d"));
+            assert!(seen.borrow().is_empty());
+        });
+
+        assert_eq!(drains.get(), 1);
+        let tags: Vec<_> = seen.borrow().iter().map(|evt| evt.tag.clone().unwrap()).collect();
+        assert_eq!(tags, vec!["open", "inner", "close"]);
+
+        // Standalone transactions drain right away
+        on.transaction(None, |v| v.clear());
+        assert_eq!(drains.get(), 2);
+    }
+
+    #[test]
+    fn with_extremes_tracks_min_and_max_seen() {
+        let on = OnMutate::new(50, |_| {}).with_extremes();
+        assert_eq!((on.min_seen(), on.max_seen()), (Some(50), Some(50)));
+
+        on.replace_many([70, 20, 90, 40]);
+        assert_eq!((on.min_seen(), on.max_seen()), (Some(20), Some(90)));
+        assert_eq!(on.get_val(), 40);
+
+        on.reset_extremes();
+        on.replace(45);
+        assert_eq!((on.min_seen(), on.max_seen()), (Some(40), Some(45)));
+
+        let untracked = OnMutate::new(1, |_| {});
+        assert_eq!(untracked.max_seen(), None);
+    }
+
+    #[test]
+    fn replace_cow_skips_the_owned_conversion_when_unchanged() {
+        use std::borrow::Cow;
+
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Config(String);
+
+        impl Clone for Config {
+            fn clone(&self) -> Self {
+                CLONES.with(|c| c.set(c.get() + 1));
+                Config(self.0.clone())
+            }
+        }
+
+        let fired = Rc::new(Cell::new(0));
+        let f2 = fired.clone();
+        let on = OnMutate::new(Config("a".into()), move |_| f2.set(f2.get() + 1));
+
+        let same = Config("a".into());
+        on.replace_cow(Cow::Borrowed(&same));
+        assert_eq!(CLONES.with(Cell::get), 0);
+        assert_eq!(fired.get(), 0);
+
+        let other = Config("b".into());
+        on.replace_cow(Cow::Borrowed(&other));
+        assert!(CLONES.with(Cell::get) > 0);
+        assert_eq!(fired.get(), 1);
+        assert_eq!(on.get_val(), other);
+    }
+
+    #[test]
+    fn new_with_sink_delivers_into_a_vec_sink() {
+        use mutation_monitor::{ClosureSink, VecSink};
+
+        let sink = VecSink::new();
+        let on = OnMutate::new_with_sink(0, sink.clone());
+
+        on.replace(1);
+        on.replace(1);
+        on.with_mut("bump".to_string(), |v| *v += 1);
+
+        let events = sink.events();
+        assert_eq!(events.iter().map(|evt| (evt.old, evt.new)).collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+        assert_eq!(events[1].tag.as_deref(), Some("bump"));
+        assert_eq!(sink.take().len(), 2);
+        assert!(sink.is_empty());
+
+        let count = Rc::new(Cell::new(0));
+        let c2 = count.clone();
+        let closure = OnMutate::new_with_sink(0, ClosureSink(move |_: &Mutate<i32>| c2.set(c2.get() + 1)));
+        closure.replace(5);
+        assert_eq!(count.get(), 1);
+    }
+
+    #[cfg(feature = "channel")]
+    #[test]
+    fn channel_sink_sends_events() {
+        use mutation_monitor::ChannelSink;
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let on = OnMutate::new_with_sink(0, ChannelSink(tx));
+        on.replace(3);
+
+        assert_eq!(rx.try_recv().unwrap().new, 3);
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn time_since_last_change_tracks_idle_time() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_clock(move || n2.get());
+        assert_eq!(on.time_since_last_change(), None);
+
+        on.replace(1);
+        now.set(start + Duration::from_secs(3));
+        assert_eq!(on.time_since_last_change(), Some(Duration::from_secs(3)));
+
+        on.replace(1); // no-op, doesn't count
+        now.set(start + Duration::from_secs(5));
+        assert_eq!(on.time_since_last_change(), Some(Duration::from_secs(5)));
+
+        on.replace(2);
+        assert_eq!(on.time_since_last_change(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn observed_setters_macro_generates_tagged_setters() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Player { name: String, score: u32 }
+
+        mutation_monitor::observed_setters! {
+            trait PlayerSetters for Player {
+                name: String => set_name_observed,
+                score: u32 => set_score_observed,
+            }
+        }
+
+        let seen: Rc<RefCell<Vec<Mutate<Player>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(Player { name: "ann".into(), score: 0 }, move |evt| s2.borrow_mut().push(evt.clone()));
+
+        on.set_score_observed(10);
+        on.set_score_observed(10); // unchanged
+        on.set_name_observed("bea".into());
+
+        let seen = seen.borrow();
+        assert_eq!(seen.iter().map(|evt| evt.tag.clone().unwrap()).collect::<Vec<_>>(), vec!["score", "name"]);
+        assert_eq!(seen[1].new, Player { name: "bea".into(), score: 10 });
+    }
+
+    #[test]
+    fn flush_on_drop_delivers_queued_events() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new)).buffered().with_flush_on_drop();
+
+        on.replace(1);
+        on.replace(2);
+        assert!(seen.borrow().is_empty());
+
+        drop(on);
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+
+        // Without it, queued events are discarded
+        let s3 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s3.borrow_mut().push(evt.new)).buffered();
+        on.replace(3);
+        drop(on);
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_patch_contains_only_changed_field() {
+        #[derive(Clone, PartialEq, serde::Serialize)]
+        struct Profile { name: String, age: u32, tags: Vec<String> }
+
+        let patches: Rc<RefCell<Vec<serde_json::Value>>> = Rc::new(RefCell::new(vec![]));
+        let p2 = patches.clone();
+        let on = OnMutate::new(Profile { name: "ann".into(), age: 30, tags: vec![] }, |_| {});
+        on.on_json_patch(move |patch| p2.borrow_mut().push(patch));
+
+        on.with_mut(None, |p| p.age = 31);
+        assert_eq!(*patches.borrow(), vec![serde_json::json!([{ "op": "replace", "path": "/age", "value": 31 }])]);
+
+        // Non-object values are replaced whole
+        let root: Rc<RefCell<Vec<serde_json::Value>>> = Rc::new(RefCell::new(vec![]));
+        let r2 = root.clone();
+        let on = OnMutate::new(1, |_| {});
+        on.on_json_patch(move |patch| r2.borrow_mut().push(patch));
+        on.replace(2);
+        assert_eq!(*root.borrow(), vec![serde_json::json!([{ "op": "replace", "path": "", "value": 2 }])]);
+    }
+
+    #[test]
+    fn retain_observed_only_emits_on_removal() {
+        use mutation_monitor::VecOnMutateExt;
+
+        let seen: Rc<RefCell<Vec<Vec<u32>>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(vec![1, 2, 3, 4], move |evt| s2.borrow_mut().push(evt.new.clone()));
+
+        on.retain_observed(|x| *x < 10);
+        assert!(seen.borrow().is_empty());
+
+        on.retain_observed(|x| x.is_multiple_of(2));
+        assert_eq!(*seen.borrow(), vec![vec![2, 4]]);
+    }
+
+    #[test]
+    fn subscribe_scoped_unsubscribes_on_drop() {
+        let hits = Rc::new(Cell::new(0));
+        let on = OnMutate::new(0, |_| {});
+
+        {
+            let h2 = hits.clone();
+            let _guard = on.subscribe_scoped(move |_| h2.set(h2.get() + 1));
+            on.replace(1);
+            assert_eq!(hits.get(), 1);
+        }
+
+        on.replace(2);
+        assert_eq!(hits.get(), 1);
+    }
+
+    #[test]
+    fn invariant_violation_calls_handler_or_rolls_back() {
+        use mutation_monitor::Violation;
+
+        let bad: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let b2 = bad.clone();
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(1, move |_| h2.set(h2.get() + 1))
+            .with_invariant(|v| *v >= 0, Violation::Call(Box::new(move |v| b2.borrow_mut().push(*v))));
+
+        on.replace(5);
+        on.replace(-3);
+        assert_eq!(*bad.borrow(), vec![-3]);
+        assert_eq!(hits.get(), 2); // Still delivered
+
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(1, move |_| h2.set(h2.get() + 1)).with_invariant(|v| *v >= 0, Violation::Rollback);
+
+        on.with_mut(None, |v| *v = -1);
+        assert_eq!(on.get_val(), 1);
+        assert_eq!(hits.get(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invariant violated")]
+    fn invariant_violation_can_panic() {
+        let on = OnMutate::new(1, |_| {}).with_invariant(|v| *v >= 0, mutation_monitor::Violation::Panic);
+        on.replace(-1);
+    }
+
+    #[test]
+    fn get_val_cached_clones_once_per_change() {
+        #[derive(Debug)]
+        struct Counted { value: i32, clones: Rc<Cell<u32>> }
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.clones.set(self.clones.get() + 1);
+                Counted { value: self.value, clones: self.clones.clone() }
+            }
+        }
+
+        impl PartialEq for Counted {
+            fn eq(&self, other: &Self) -> bool {
+                self.value == other.value
+            }
+        }
+
+        let clones = Rc::new(Cell::new(0));
+        let on = OnMutate::new(Counted { value: 1, clones: clones.clone() }, |_| {});
+
+        let reads: Vec<i32> = (0..3).map(|_| on.get_val_cached().value).collect();
+        assert_eq!(reads, vec![1, 1, 1]);
+        assert_eq!(clones.get(), 1);
+
+        on.with_mut(None, |v| v.value = 2);
+        clones.set(0);
+
+        assert_eq!(on.get_val_cached().value, 2);
+        assert_eq!(on.get_val_cached().value, 2);
+        assert_eq!(clones.get(), 1);
+    }
+
+    #[test]
+    fn subscribe_stable_fires_once_after_cascade() {
+        let on: Rc<RefCell<Option<Rc<OnMutate<i32>>>>> = Rc::new(RefCell::new(None));
+        let weak = on.clone();
+
+        // Values above 10 keep halving themselves, settling at 80 -> 40 -> 20 -> 10
+        let monitor = Rc::new(OnMutate::new(0, move |evt: &Mutate<i32>| {
+            if evt.new > 10 {
+                if let Some(on) = weak.borrow().as_ref() {
+                    on.replace(evt.new / 2);
+                }
+            }
+        }));
+        *on.borrow_mut() = Some(monitor.clone());
+
+        let stable: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = stable.clone();
+        monitor.subscribe_stable(move |v| s2.borrow_mut().push(*v));
+
+        monitor.replace(80);
+        assert_eq!(*stable.borrow(), vec![10]);
+
+        // Settling on the same value again doesn't fire
+        monitor.replace(20);
+        assert_eq!(*stable.borrow(), vec![10]);
+
+        on.borrow_mut().take();
+    }
+
+    #[cfg(feature = "timestamps")]
+    #[test]
+    fn history_max_age_trims_old_entries() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_clock(move || n2.get()).with_history_max_age(Duration::from_secs(10));
+
+        for (secs, value) in [(0, 1), (5, 2), (12, 3), (20, 4)] {
+            now.set(start + Duration::from_secs(secs));
+            on.replace(value);
+        }
+
+        // At t=20, only t=12 and t=20 are within 10s
+        assert_eq!(on.history().iter().map(|evt| evt.new).collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn finite_validation_rolls_back_nan() {
+        use mutation_monitor::VecOnMutateExt;
+
+        let rejected: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(vec![]));
+        let r2 = rejected.clone();
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(vec![1.0, 2.0], move |_| h2.set(h2.get() + 1))
+            .with_finite_validation(move |v| r2.borrow_mut().push(v.len()));
+
+        on.push_observed(3.0);
+        on.push_observed(f64::NAN);
+
+        assert_eq!(on.get_val(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(*rejected.borrow(), vec![4]);
+        assert_eq!(hits.get(), 1);
+    }
+
+    #[test]
+    fn take_callback_queues_until_restored() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new));
+
+        let callback = on.take_callback().expect("callback is attached");
+        assert!(on.take_callback().is_none());
+
+        on.replace(1);
+        on.replace(2);
+        assert!(seen.borrow().is_empty());
+        assert_eq!(on.queue_len(), 2);
+
+        on.restore_callback(callback);
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+
+        on.replace(3);
+        assert_eq!(*seen.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn monotonic_reports_regressions() {
+        let regressions: Rc<RefCell<Vec<(u64, u64)>>> = Rc::new(RefCell::new(vec![]));
+        let r2 = regressions.clone();
+        let on = OnMutate::new((10u64, "boot"), |_| {})
+            .with_monotonic(|v| v.0, move |old, new| {
+                r2.borrow_mut().push((old, new));
+                false
+            });
+
+        on.replace((20, "tick"));
+        on.replace((15, "skew"));
+        on.replace((15, "skew again"));
+
+        assert_eq!(*regressions.borrow(), vec![(20, 15)]);
+        assert_eq!(on.get_val(), (15, "skew again"));
+    }
+
+    #[test]
+    fn tag_coalescing_merges_adjacent_runs() {
+        type Seen = Rc<RefCell<Vec<(Option<String>, i32, i32)>>>;
+
+        let seen: Seen = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push((evt.tag.clone(), evt.old, evt.new)))
+            .buffered()
+            .with_tag_coalescing();
+
+        for (value, tag) in [(1, "a"), (2, "a"), (3, "b"), (4, "a")] {
+            on.with_mut(tag.to_string(), |v| *v = value);
+        }
+
+        on.drain_now();
+
+        let tag = |t: &str| Some(t.to_string());
+        assert_eq!(*seen.borrow(), vec![(tag("a"), 0, 2), (tag("b"), 2, 3), (tag("a"), 3, 4)]);
+    }
+
+    #[test]
+    fn lazy_eq_compares_at_drain() {
+        #[derive(Clone, Debug)]
+        struct Expensive { value: i32, comparisons: Rc<Cell<u32>> }
+
+        impl PartialEq for Expensive {
+            fn eq(&self, other: &Self) -> bool {
+                self.comparisons.set(self.comparisons.get() + 1);
+                self.value == other.value
+            }
+        }
+
+        let comparisons = Rc::new(Cell::new(0));
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(Expensive { value: 1, comparisons: comparisons.clone() }, move |_| h2.set(h2.get() + 1))
+            .buffered()
+            .with_lazy_eq();
+
+        on.replace(Expensive { value: 1, comparisons: comparisons.clone() });
+        on.replace(Expensive { value: 2, comparisons: comparisons.clone() });
+        assert_eq!(comparisons.get(), 0);
+        assert_eq!(on.queue_len(), 2);
+
+        on.drain_now();
+        assert_eq!(comparisons.get(), 2);
+        assert_eq!(hits.get(), 1); // The equal replace was dropped
+    }
+
+    #[test]
+    fn ptr_eq_counts_distinct_allocations_as_changes() {
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let by_value = OnMutate::new(Rc::new(vec![1, 2]), move |_| h2.set(h2.get() + 1));
+        by_value.replace(Rc::new(vec![1, 2]));
+        assert_eq!(hits.get(), 0);
+
+        let h3 = hits.clone();
+        let by_ptr = OnMutate::new(Rc::new(vec![1, 2]), move |_| h3.set(h3.get() + 1)).with_ptr_eq();
+        by_ptr.replace(Rc::new(vec![1, 2]));
+        assert_eq!(hits.get(), 1);
+
+        // The same allocation is still unchanged
+        by_ptr.replace(by_ptr.get_val());
+        assert_eq!(hits.get(), 1);
+    }
+
+    #[test]
+    fn with_ref_and_read_guard_read_in_place() {
+        let on = OnMutate::new((String::from("name"), 7), |_| {});
+
+        assert_eq!(on.with_ref(|v| v.0.len()), 4);
+
+        let guard = on.read_guard();
+        assert_eq!(guard.1, 7);
+        drop(guard);
+
+        on.with_mut(None, |v| v.1 = 8);
+        assert_eq!(on.with_ref(|v| v.1), 8);
+    }
+
+    #[test]
+    fn replace_expect_change_accepts_changes() {
+        let hits = Rc::new(Cell::new(0));
+        let h2 = hits.clone();
+        let on = OnMutate::new(1, move |_| h2.set(h2.get() + 1));
+
+        on.replace_expect_change(2);
+        assert_eq!(hits.get(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "nothing changed")]
+    fn replace_expect_change_panics_on_equal_value() {
+        let on = OnMutate::new(1, |_| {});
+        on.replace_expect_change(1);
+    }
+
+    #[test]
+    fn histogram_counts_delivered_values_per_bucket() {
+        let on = OnMutate::new(0, |_| {}).with_histogram(vec![10, 20]);
+        assert_eq!(on.histogram(), vec![0, 0, 0]);
+
+        for value in [1, 5, 10, 15, 19, 20, 99] {
+            on.replace(value);
+        }
+
+        assert_eq!(on.histogram(), vec![2, 3, 2]);
+        assert!(OnMutate::new(0, |_| {}).histogram().is_empty());
+    }
+
+    #[test]
+    fn subscribe_tag_only_sees_matching_events() {
+        let a: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let b: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let (a2, b2) = (a.clone(), b.clone());
+        let on = OnMutate::new(0, |_| {});
+
+        on.subscribe_tag("a", move |evt| a2.borrow_mut().push(evt.new));
+        on.subscribe_tag("b", move |evt| b2.borrow_mut().push(evt.new));
+
+        on.with_mut("a".to_string(), |v| *v = 1);
+        on.with_mut("b".to_string(), |v| *v = 2);
+        on.replace(3);
+        on.with_mut("a".to_string(), |v| *v = 4);
+
+        assert_eq!(*a.borrow(), vec![1, 4]);
+        assert_eq!(*b.borrow(), vec![2]);
+    }
+
+    #[test]
+    fn guard_snapshot_reads_interim_value() {
+        let seen: Rc<RefCell<Vec<(i32, i32)>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(1, move |evt| s2.borrow_mut().push((evt.old, evt.new)));
+
+        let mut guard = on.with_guard();
+        *guard = 5;
+        assert_eq!(guard.snapshot(), 5);
+
+        *guard += 1;
+        assert_eq!(guard.snapshot(), 6);
+        drop(guard);
+
+        assert_eq!(*seen.borrow(), vec![(1, 6)]);
+    }
+
+    #[test]
+    fn recent_dedup_suppresses_oscillation() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new)).with_recent_dedup(2);
+
+        // The first full cycle gets through, later flips don't
+        for value in [1, 0, 1, 0] {
+            on.replace(value);
+        }
+
+        assert_eq!(*seen.borrow(), vec![1, 0]);
+        assert_eq!(on.get_val(), 0);
+
+        // A new value pushes the oldest out of the window
+        on.replace(2);
+        on.replace(1);
+        assert_eq!(*seen.borrow(), vec![1, 0, 2, 1]);
+    }
+
+    #[test]
+    fn new_shared_callback_can_mutate_its_monitor() {
+        let on = OnMutate::new_shared(0, |on, evt| {
+            if evt.new < 3 {
+                on.with_mut(None, |v| *v += 1);
+            }
+        });
+
+        on.with_mut(None, |v| *v += 1);
+        assert_eq!(on.get_val(), 3);
+
+        let shared = OnMutate::new(1, |_| {}).into_shared();
+        let other = shared.clone();
+        other.replace(2);
+        assert_eq!(shared.get_val(), 2);
+    }
+
+    #[test]
+    fn transition_tracking_counts_pairs() {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        enum Light { Red, Green, Yellow }
+
+        let on = OnMutate::new(Light::Red, |_| {}).with_transition_tracking();
+
+        for light in [Light::Green, Light::Yellow, Light::Red, Light::Green, Light::Red] {
+            on.replace(light);
+        }
+
+        let transitions = on.transitions();
+        assert_eq!(transitions.len(), 4);
+        assert_eq!(transitions[&(Light::Red, Light::Green)], 2);
+        assert_eq!(transitions[&(Light::Green, Light::Yellow)], 1);
+        assert_eq!(transitions[&(Light::Yellow, Light::Red)], 1);
+        assert_eq!(transitions[&(Light::Green, Light::Red)], 1);
+    }
+
+    #[test]
+    fn subscribe_stable_restarts_when_a_stable_callback_mutates() {
+        let a_seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let b_seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let on = Rc::new(OnMutate::new(0, |_| {}));
+
+        let (a2, weak) = (a_seen.clone(), Rc::downgrade(&on));
+        on.subscribe_stable(move |v| {
+            a2.borrow_mut().push(*v);
+
+            if *v == 1 {
+                weak.upgrade().unwrap().replace(2);
+            }
+        });
+
+        let b2 = b_seen.clone();
+        on.subscribe_stable(move |v| b2.borrow_mut().push(*v));
+
+        on.replace(1);
+        assert_eq!(*a_seen.borrow(), vec![1, 2]);
+        assert_eq!(*b_seen.borrow(), vec![2]);
+    }
+
+    #[test]
+    fn checkpoint_delivers_under_block_policy() {
+        use mutation_monitor::OverflowPolicy;
+
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new)).with_max_queue(1, OverflowPolicy::Block);
+
+        // Each checkpoint delivers on its own, so the cap is never hit while the guard holds the value
+        let mut guard = on.with_guard();
+        for value in 1..=3 {
+            *guard = value;
+            guard.checkpoint(None);
+            assert_eq!(seen.borrow().last(), Some(&value));
+        }
+
+        drop(guard);
+        assert_eq!(*seen.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn rolled_back_changes_dont_count() {
+        use mutation_monitor::Violation;
+
+        let on = OnMutate::new(0, |_| {}).with_invariant(|v| *v < 10, Violation::Rollback);
+        assert_eq!(on.replace_many([1, 50, 2]), 2);
+        assert_eq!(on.get_val(), 2);
+
+        let labels: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec![]));
+        let l2 = labels.clone();
+        let on = OnMutate::new(0, |_| {})
+            .with_invariant(|v| *v < 10, Violation::Log)
+            .with_inspect(move |label| l2.borrow_mut().push(label.to_string()));
+
+        on.replace(50);
+        assert!(labels.borrow().iter().any(|label| label == "invariant violated"));
+        assert_eq!(on.get_val(), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "nothing changed")]
+    fn replace_expect_change_panics_on_rollback() {
+        let on = OnMutate::new(0, |_| {}).with_invariant(|v| *v < 10, mutation_monitor::Violation::Rollback);
+        on.replace_expect_change(50);
+    }
+
+    #[test]
+    fn subscribe_debounced_drops_bursts_that_end_where_they_started() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+        let now = Rc::new(Cell::new(start));
+        let n2 = now.clone();
+        let on = OnMutate::new(0, |_| {}).with_clock(move || n2.get());
+
+        let debounced: Rc<RefCell<Vec<(i32, i32)>>> = Rc::new(RefCell::new(vec![]));
+        let d2 = debounced.clone();
+        on.subscribe_debounced(Duration::from_millis(100), move |evt| d2.borrow_mut().push((evt.old, evt.new)));
+
+        on.replace(1);
+        on.replace(0);
+
+        now.set(start + Duration::from_millis(200));
+        assert!(!on.poll());
+        assert!(debounced.borrow().is_empty());
+
+        // The next burst starts afresh
+        on.replace(2);
+        now.set(start + Duration::from_millis(400));
+        assert!(on.poll());
+        assert_eq!(*debounced.borrow(), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn recent_dedup_covers_every_replace_path() {
+        use std::borrow::Cow;
+
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new)).with_recent_dedup(2);
+
+        assert_eq!(on.replace_many([1, 0, 1]), 2);
+        on.replace_cow(Cow::Owned(1));
+        on.replace_cow(Cow::Owned(0));
+        assert_eq!(on.replace_and_get(1), 0);
+
+        assert_eq!(*seen.borrow(), vec![1, 0]);
+    }
+
+    #[test]
+    fn cached_reads_see_writes_deemed_unchanged() {
+        let on = OnMutate::new(1.0, |_| {}).with_float_epsilon(0.5);
+        let doubled = on.computed(|v| v * 2.0);
+
+        assert_eq!(*on.get_val_cached(), 1.0);
+        assert_eq!(doubled.get(), 2.0);
+
+        on.with_mut(None, |v| *v = 1.2); // Within epsilon: no event, but the value was written
+        assert_eq!(on.current_seq(), 0);
+        assert_eq!(*on.get_val_cached(), 1.2);
+        assert_eq!(doubled.get(), 2.4);
+    }
+
+    #[test]
+    fn repeated_with_histogram_uses_latest_bounds() {
+        let on = OnMutate::new(0, |_| {}).with_histogram(vec![100]).with_histogram(vec![10]);
+        on.replace(5);
+        assert_eq!(on.histogram(), vec![1, 0]);
+    }
+
+    #[test]
+    fn repeated_transition_tracking_counts_once() {
+        let on = OnMutate::new(0, |_| {}).with_transition_tracking().with_transition_tracking();
+        on.replace(1);
+        assert_eq!(on.transitions()[&(0, 1)], 1);
+    }
+
+    #[test]
+    fn repeated_with_extremes_tracks_once() {
+        let on = OnMutate::new(5, |_| {}).with_extremes();
+        on.replace(9);
+        let on = on.with_extremes();
+        on.replace(1);
+
+        assert_eq!((on.min_seen(), on.max_seen()), (Some(1), Some(9)));
+    }
+
+    #[test]
+    fn tag_coalescing_keeps_round_trips_and_kinds() {
+        use mutation_monitor::EventKind;
+
+        let seen: Rc<RefCell<Vec<(i32, i32, EventKind)>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push((evt.old, evt.new, evt.kind)))
+            .buffered()
+            .with_tag_coalescing();
+
+        on.with_mut("a".to_string(), |v| *v = 1);
+        on.with_mut("a".to_string(), |v| *v = 0);
+        on.with_mut_ctx(|v, ctx| {
+            *v = 0;
+            ctx.tag("b");
+            ctx.force();
+        });
+        on.with_mut("b".to_string(), |v| *v = 2);
+
+        on.drain_now();
+        assert_eq!(*seen.borrow(), vec![(0, 0, EventKind::Changed), (0, 2, EventKind::Forced)]);
+    }
+
+    #[test]
+    fn monotonic_handler_can_reject_regressions() {
+        let seen: Rc<RefCell<Vec<u64>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(10u64, move |evt| s2.borrow_mut().push(evt.new))
+            .with_filter(|_| false) // Checked as changes are queued, so filtering doesn't hide regressions
+            .with_monotonic(|v| *v, |old, new| old - new > 5);
+
+        assert_eq!(on.replace_many([20, 18, 3]), 2);
+        assert_eq!(on.get_val(), 18);
+        assert!(seen.borrow().is_empty());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn dropped_streams_remove_their_listener() {
+        let on = OnMutate::new(0, |_| {});
+
+        for _ in 0..3 {
+            drop(on.stream());
+        }
+
+        assert_eq!(on.subscriber_count(), 3);
+
+        // The next event retires every listener whose stream is gone
+        on.replace(1);
+        assert_eq!(on.subscriber_count(), 0);
+
+        let _live = on.stream();
+        on.replace(2);
+        assert_eq!(on.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn array_index_changes_go_through_the_pipeline() {
+        use mutation_monitor::{IndexChange, Violation};
+
+        type Changes = Rc<RefCell<Vec<IndexChange<u8>>>>;
+        let changes: Changes = Rc::new(RefCell::new(vec![]));
+
+        // A buffered monitor holds index events until it drains
+        let on = OnMutate::new([0u8; 8], |_| {}).buffered();
+        let c2 = changes.clone();
+        on.on_index_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.set_index_observed(1, 5);
+        assert!(changes.borrow().is_empty());
+        on.drain_now();
+        assert_eq!(changes.borrow().len(), 1);
+
+        // Paused ones discard them
+        on.pause_during(|| on.set_index_observed(2, 5));
+        on.drain_now();
+        assert_eq!(changes.borrow().len(), 1);
+        assert_eq!(on.get_val()[2], 5);
+
+        // A shared value's baseline follows, so polling finds nothing external
+        let whole = Rc::new(Cell::new(0));
+        let w2 = whole.clone();
+        let shared = Rc::new(RefCell::new([0u8; 8]));
+        let on = OnMutate::from_shared(shared, move |_| w2.set(w2.get() + 1));
+
+        on.set_index_observed(3, 9);
+        assert!(!on.poll_external());
+        assert_eq!(whole.get(), 0);
+
+        // Invariants see the whole array and can roll the slot back
+        let on = OnMutate::new([0u8; 8], |_| {}).with_invariant(|v: &[u8; 8]| v.iter().all(|x| *x < 10), Violation::Rollback);
+        let c2 = changes.clone();
+        on.on_index_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.set_index_observed(0, 50);
+        assert_eq!(on.get_val()[0], 0);
+        assert_eq!(changes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn range_changes_go_through_the_pipeline() {
+        use mutation_monitor::{RangeChange, Violation};
+
+        type Changes = Rc<RefCell<Vec<RangeChange<i32>>>>;
+        let changes: Changes = Rc::new(RefCell::new(vec![]));
+
+        // Batched range events wait for the batch to end
+        let on = OnMutate::new(vec![1, 2, 3], |_| {});
+        let c2 = changes.clone();
+        on.on_range_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.batch(|| {
+            on.replace_range_observed(0..1, vec![7, 7]);
+            assert!(changes.borrow().is_empty());
+        });
+        assert_eq!(changes.borrow().len(), 1);
+
+        // A shared value's baseline follows
+        let shared = Rc::new(RefCell::new(vec![1, 2, 3]));
+        let on = OnMutate::from_shared(shared, |_| panic!("no whole-vector event expected"));
+        on.replace_range_observed(1..3, vec![]);
+        assert!(!on.poll_external());
+
+        // Rolled-back splices are undone and never reported
+        let on = OnMutate::new(vec![1, 2, 3], |_| {}).with_invariant(|v: &Vec<i32>| v.len() <= 3, Violation::Rollback);
+        let c2 = changes.clone();
+        on.on_range_change(move |change| c2.borrow_mut().push(change.clone()));
+
+        on.replace_range_observed(1..2, vec![8, 9]);
+        assert_eq!(on.get_val(), vec![1, 2, 3]);
+        assert_eq!(changes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn pause_during_resumes_after_a_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new));
+
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| {
+            on.pause_during(|| {
+                on.replace(1);
+                panic!("bookkeeping failed");
+            })
+        }));
+        assert!(caught.is_err());
+
+        on.replace(2);
+        assert_eq!(*seen.borrow(), vec![2]);
+    }
+
+    #[test]
+    fn checkpoint_defers_stable_subscribers_to_the_drop() {
+        let stable: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = stable.clone();
+        let on = OnMutate::new(0, |_| {});
+        on.subscribe_stable(move |v| s2.borrow_mut().push(*v));
+
+        let mut guard = on.with_guard();
+        *guard = 1;
+        guard.checkpoint(None);
+        assert!(stable.borrow().is_empty());
+
+        drop(guard);
+        assert_eq!(*stable.borrow(), vec![1]);
+    }
+
+    #[test]
+    fn recent_dedup_applied_keeps_the_value() {
+        let seen: Rc<RefCell<Vec<i32>>> = Rc::new(RefCell::new(vec![]));
+        let s2 = seen.clone();
+        let on = OnMutate::new(0, move |evt| s2.borrow_mut().push(evt.new)).with_recent_dedup_applied(2);
+
+        for value in [1, 2, 1] {
+            on.replace(value);
+        }
+
+        // The last flip is silent, but it did happen
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+        assert_eq!(on.get_val(), 1);
+    }
+
+    #[test]
+    fn worker_thread_panic_surfaces_on_the_producer() {
+        use mutation_monitor::SyncOnMutate;
+        use std::panic::{self, AssertUnwindSafe};
+        use std::thread;
+        use std::time::Duration;
+
+        let on = SyncOnMutate::new(0, |_: &Mutate<i32>| panic!("callback failed")).with_worker_thread();
+        on.replace(1);
+
+        // Once the worker has died, the next send fails and re-raises its panic
+        let payload = (2..1000).find_map(|value| {
+            thread::sleep(Duration::from_millis(1));
+            panic::catch_unwind(AssertUnwindSafe(|| on.replace(value))).err()
+        });
+
+        let payload = payload.expect("a send should fail once the worker is gone");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback failed"));
+    }
+
+    #[test]
+    #[should_panic(expected = "nested mutation")]
+    fn replace_range_observed_inside_a_guard_reports_nesting() {
+        let on = OnMutate::new(vec![1, 2, 3], |_| {});
+        let _guard = on.with_guard();
+        on.replace_range_observed(0..1, vec![]);
+    }
 }